@@ -0,0 +1,61 @@
+//! Cost of `created_time()` over 10k pushes, compared with reading cached `DateTime`s.
+//!
+//! Run with `cargo bench --bench time_conversion`.
+
+extern crate pshbullet_client;
+extern crate chrono;
+extern crate serde_json;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use chrono::prelude::*;
+use pshbullet_client::push::Response;
+
+const PUSHES: usize = 10_000;
+const ROUNDS: u32 = 100;
+
+fn pushes() -> Vec<Response> {
+    (0..PUSHES)
+        .map(|i| {
+            let created = 1.412047948579029e+09 + i as f64 * 0.001;
+            serde_json::from_value(serde_json::json!({
+                "active": true,
+                "created": created,
+                "modified": created,
+                "direction": "self",
+                "dismissed": false,
+                "iden": format!("push{}", i),
+                "type": "note",
+                "body": "bench"
+            })).unwrap()
+        })
+        .collect()
+}
+
+fn per_call(elapsed: Duration) -> f64 {
+    elapsed.as_nanos() as f64 / (PUSHES as f64 * ROUNDS as f64)
+}
+
+fn main() {
+    let pushes = pushes();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for push in &pushes {
+            black_box(push.created_time());
+        }
+    }
+    let converted = start.elapsed();
+
+    let cached: Vec<DateTime<Utc>> = pushes.iter().map(Response::created_time).collect();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        for t in &cached {
+            black_box(*t);
+        }
+    }
+    let read = start.elapsed();
+
+    println!("created_time(): {:.1}ns per call", per_call(converted));
+    println!("cached DateTime: {:.1}ns per read", per_call(read));
+}
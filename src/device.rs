@@ -1,13 +1,13 @@
 //! Pushbullet Device API
 //!
 //! See [`PushbulletClient::list_devices()`](../struct.PushbulletClient.html#method.list_devices)
+//! or [`PushbulletClient::create_device()`](../struct.PushbulletClient.html#method.create_device)
 
 use super::*;
+use reqwest::Url;
 
 
-lazy_static! {
-    static ref DEVICES_URL: String = format!("{}devices", BASE_URL);
-}
+const DEVICES_PATH: &str = "devices";
 
 /// Response type of the device API
 #[derive(Deserialize, Debug)]
@@ -22,6 +22,9 @@ pub struct Response {
     pub modified: f64,
     /// Icon to use for this device, can be an arbitrary string.
     pub icon: String,
+    /// If `true`, the device can receive pushes
+    #[serde(default)]
+    pub pushable: bool,
 
     /// Version of the Pushbullet application installed on the device
     pub app_version: Option<u32>,
@@ -33,9 +36,66 @@ pub struct Response {
     pub nickname: Option<String>,
     /// Platform-specific push token.
     pub push_token: Option<String>,
+    /// Fingerprint for the device, used by apps to avoid duplicate devices
+    pub fingerprint: Option<String>,
+    /// Fingerprint for the device's end-to-end encryption key
+    pub key_fingerprint: Option<String>,
+    /// `true` if the device can send SMS messages
+    pub has_sms: Option<bool>,
+    /// "enabled" if the device supports remote file access
+    pub remote_files: Option<String>,
+    /// Platform of the device, e.g. "ios", "android" or "chrome"
+    pub kind: Option<String>,
+    /// Same as `kind`, set by older clients
+    #[serde(rename = "type")]
+    pub device_type: Option<String>,
+}
+
+/// Icon of a device, see [`Response::icon_kind()`](struct.Response.html#method.icon_kind)
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceIcon {
+    /// "desktop"
+    Desktop,
+    /// "browser"
+    Browser,
+    /// "website"
+    Website,
+    /// "laptop"
+    Laptop,
+    /// "tablet"
+    Tablet,
+    /// "phone"
+    Phone,
+    /// "watch"
+    Watch,
+    /// "system"
+    System,
+    /// An icon not known to this crate
+    Other(String),
+}
+
+impl<'a> From<&'a str> for DeviceIcon {
+    fn from(s: &'a str) -> DeviceIcon {
+        match s {
+            "desktop" => DeviceIcon::Desktop,
+            "browser" => DeviceIcon::Browser,
+            "website" => DeviceIcon::Website,
+            "laptop" => DeviceIcon::Laptop,
+            "tablet" => DeviceIcon::Tablet,
+            "phone" => DeviceIcon::Phone,
+            "watch" => DeviceIcon::Watch,
+            "system" => DeviceIcon::System,
+            other => DeviceIcon::Other(other.to_owned())
+        }
+    }
 }
 
 impl Response {
+    /// Get `icon` field as a `DeviceIcon`.
+    pub fn icon_kind(&self) -> DeviceIcon {
+        DeviceIcon::from(self.icon.as_str())
+    }
+
     /// Get `created` field as DateTime.
     pub fn created_time(&self) -> DateTime<Utc> {
         float_unix_time2date_time(self.created)
@@ -47,6 +107,71 @@ impl Response {
     }
 }
 
+impl<'a> From<&'a Response> for push::Target {
+    /// Target the push to this device.
+    fn from(device: &'a Response) -> push::Target {
+        push::Target::Device(device.iden.clone())
+    }
+}
+
+/// Keep only active (non-deleted) devices.
+pub fn active_only(devices: Vec<Response>) -> Vec<Response> {
+    devices.into_iter().filter(|d| d.active).collect()
+}
+
+/// Keep only devices whose `kind` (or `type`) is `kind`, including deleted devices.
+pub fn of_kind(devices: Vec<Response>, kind: &str) -> Vec<Response> {
+    devices.into_iter()
+        .filter(|d| d.kind.as_ref().or(d.device_type.as_ref()).is_some_and(|k| k == kind))
+        .collect()
+}
+
+/// Sort devices by nickname, ignoring case. Devices without a nickname come last.
+pub fn sorted_by_nickname(mut devices: Vec<Response>) -> Vec<Response> {
+    devices.sort_by_cached_key(|d| (d.nickname.is_none(), d.nickname.as_ref().map(|n| n.to_lowercase())));
+    devices
+}
+
+/// Parameters of a device to create,
+/// see [`PushbulletClient::create_device()`](../struct.PushbulletClient.html#method.create_device)
+///
+/// Fields left `None` are not sent.
+#[derive(Default, Debug)]
+pub struct CreateRequest<'a> {
+    /// Name to use when displaying the device
+    pub nickname: &'a str,
+    /// Model of the device
+    pub model: Option<&'a str>,
+    /// Manufacturer of the device
+    pub manufacturer: Option<&'a str>,
+    /// Icon to use for this device, e.g. "phone" or "system"
+    pub icon: Option<&'a str>,
+    /// Platform of the device, e.g. "android" or "stream"
+    pub kind: Option<&'a str>,
+    /// `true` if the device can send SMS messages
+    pub has_sms: Option<bool>,
+}
+
+fn create_json(request: &CreateRequest) -> Value {
+    let mut json = json!({ "nickname": request.nickname });
+    let map = json.as_object_mut().unwrap();
+    let fields = [
+        ("model", request.model),
+        ("manufacturer", request.manufacturer),
+        ("icon", request.icon),
+        ("kind", request.kind),
+    ];
+    for (key, value) in fields.iter() {
+        if let Some(value) = value {
+            map.insert(key.to_string(), json!(value));
+        }
+    }
+    if let Some(has_sms) = request.has_sms {
+        map.insert(String::from("has_sms"), json!(has_sms));
+    }
+    json
+}
+
 #[derive(Deserialize, Debug)]
 struct ResponseVec {
     pub devices: Vec<Response>
@@ -57,7 +182,17 @@ pub type Result = ::std::result::Result<(Vec<Response>, ResponseHeaders), Box<dy
 impl PushbulletClient {
     /// Get a list of devices belonging to the current user.
     pub fn list_devices(&self) -> Result {
-        match self.get(&*DEVICES_URL) {
+        self.list_devices_with_params(&[])
+    }
+
+    /// Get a list of devices with additional query parameters, for parameters of the
+    /// API not modeled by this crate.
+    pub fn list_devices_with_params(&self, params: &[(&str, &str)]) -> Result {
+        let mut url = Url::parse(&self.api_url(DEVICES_PATH))?;
+        if !params.is_empty() {
+            url.query_pairs_mut().extend_pairs(params);
+        }
+        match self.get(url.as_str()) {
             Ok((raw_response, headers)) => {
                 let r: ResponseVec = serde_json::from_reader(raw_response)?;
                 Ok((r.devices, headers))
@@ -73,6 +208,34 @@ impl PushbulletClient {
             Err(e) => Err(e)
         }
     }
+
+    /// Create a device, e.g. a bridge that shows up in the official apps.
+    pub fn create_device(&self, request: &CreateRequest)
+                         -> ::std::result::Result<(Response, ResponseHeaders), Box<dyn Error>> {
+        let json = create_json(request);
+        debug!("json: {}", json);
+        match self.post(&self.api_url(DEVICES_PATH), json) {
+            Ok((raw_response, headers)) => {
+                let r: Response = serde_json::from_reader(raw_response)?;
+                Ok((r, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Get the devices of the current user with the platform `kind`, e.g. "ios" or "android".
+    ///
+    /// Deleted devices are included, see [`active_only()`](device/fn.active_only.html) to drop them.
+    pub fn list_devices_by_kind(&self, kind: &str) -> Result {
+        self.list_devices().map(|(devices, headers)| (of_kind(devices, kind), headers))
+    }
+
+    /// Get the active devices of the current user that can receive pushes.
+    pub fn list_pushable_devices(&self) -> Result {
+        self.list_devices().map(|(devices, headers)| {
+            (devices.into_iter().filter(|d| d.active && d.pushable).collect(), headers)
+        })
+    }
 }
 
 
@@ -87,20 +250,98 @@ mod tests {
         assert_eq!(deserialized.devices.len(), 2);
         let r = &deserialized.devices[0];
         assert_eq!(r.app_version, Some(8623));
-        assert_eq!(r.active, true);
+        assert!(r.active);
         assert_eq!(r.iden, "ujpah72o0sjAoRtnM0jc");
+        assert_eq!(r.has_sms, Some(true));
+        assert_eq!(deserialized.devices[1].has_sms, None);
+        assert!(r.pushable);
+        assert!(!deserialized.devices[1].pushable);
 
         assert_eq!(r.modified, 1.412047948579031e+09);
 //        println!("{}", r.modified_time().to_rfc3339());
-        let diff = r.modified_time().signed_duration_since(Utc.ymd(2014, 9, 30).and_hms(3, 32, 28));
+        let diff = r.modified_time().signed_duration_since(Utc.with_ymd_and_hms(2014, 9, 30, 3, 32, 28).unwrap());
         assert!((diff.num_milliseconds() - 579).abs() < 10);
 
         assert_eq!(r.created, 1.412047948579029e+09);
 //        println!("{}", r.created_time().to_rfc3339());
-        let diff = r.created_time().signed_duration_since(Utc.ymd(2014, 9, 30).and_hms(3, 32, 28));
+        let diff = r.created_time().signed_duration_since(Utc.with_ymd_and_hms(2014, 9, 30, 3, 32, 28).unwrap());
         assert!((diff.num_milliseconds() - 579).abs() < 10);
     }
 
+    #[test]
+    fn active_only_and_sort_test() {
+        let deserialized: ResponseVec = serde_json::from_str(DEVICES_RESULT).unwrap();
+        let mut devices = deserialized.devices;
+        devices.reverse();
+        let devices = sorted_by_nickname(devices);
+        assert_eq!(devices[0].iden, "ujpah72o0sjAoRtnM0jc");
+        assert_eq!(devices[1].iden, "ujCf8vfVeUumdk2AXMrt7Y");
+
+        let devices = active_only(devices);
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].iden, "ujpah72o0sjAoRtnM0jc");
+    }
+
+    #[test]
+    fn of_kind_test() {
+        let deserialized: ResponseVec = serde_json::from_str(DEVICES_RESULT).unwrap();
+        assert_eq!(deserialized.devices[0].kind, Some(String::from("ios")));
+        assert_eq!(deserialized.devices[0].device_type, Some(String::from("ios")));
+        let devices = of_kind(deserialized.devices, "ios");
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].iden, "ujpah72o0sjAoRtnM0jc");
+    }
+
+    #[test]
+    fn icon_kind_test() {
+        let deserialized: ResponseVec = serde_json::from_str(DEVICES_RESULT).unwrap();
+        assert_eq!(deserialized.devices[0].icon_kind(), DeviceIcon::Phone);
+        assert_eq!(DeviceIcon::from("browser"), DeviceIcon::Browser);
+        assert_eq!(DeviceIcon::from("watch"), DeviceIcon::Watch);
+        assert_eq!(DeviceIcon::from("toaster"), DeviceIcon::Other(String::from("toaster")));
+    }
+
+    #[test]
+    fn list_devices_with_params_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/devices?active=true")
+            .with_body(DEVICES_RESULT)
+            .create();
+        let (devices, _) = client.list_devices_with_params(&[("active", "true")]).unwrap();
+        assert_eq!(devices.len(), 2);
+    }
+
+    #[test]
+    fn create_json_test() {
+        let request = CreateRequest { nickname: "Stream", ..Default::default() };
+        assert_eq!(create_json(&request), json!({"nickname": "Stream"}));
+
+        let request = CreateRequest {
+            nickname: "SMS bridge",
+            model: Some("Bridge"),
+            icon: Some("phone"),
+            kind: Some("android"),
+            has_sms: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(create_json(&request), json!({
+            "nickname": "SMS bridge",
+            "model": "Bridge",
+            "icon": "phone",
+            "kind": "android",
+            "has_sms": true
+        }));
+    }
+
+    #[test]
+    fn target_from_device_test() {
+        let deserialized: ResponseVec = serde_json::from_str(DEVICES_RESULT).unwrap();
+        match push::Target::from(&deserialized.devices[0]) {
+            push::Target::Device(iden) => assert_eq!(iden, "ujpah72o0sjAoRtnM0jc"),
+            t => panic!("unexpected target: {:?}", t)
+        }
+    }
+
     const DEVICES_RESULT: &str = r#"
 {
   "devices": [
@@ -117,6 +358,7 @@ mod tests {
       "type": "ios",
       "kind": "ios",
       "pushable": true,
+      "has_sms": true,
       "icon": "phone"
     },
     {
@@ -0,0 +1,119 @@
+//! End-to-end encryption of ephemerals
+//!
+//! Encrypted pushes received from the stream are decrypted as well.
+//!
+//! Messages are encrypted with AES-256-GCM, using a key derived from the user's encryption
+//! password with PBKDF2-SHA256. Requires the `encryption` feature.
+//! See [`PushbulletClient::set_encryption_password()`](../struct.PushbulletClient.html#method.set_encryption_password)
+
+use super::*;
+use aes_gcm::aead::{AeadCore, AeadInPlace, OsRng};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, Tag};
+use sha2::Sha256;
+
+
+/// PBKDF2 iterations of the official apps
+const PBKDF2_ROUNDS: u32 = 30000;
+
+/// First byte of an encrypted message
+const VERSION: u8 = b'1';
+const TAG_LEN: usize = 16;
+const IV_LEN: usize = 12;
+
+/// Key of end-to-end encryption
+#[derive(Clone)]
+pub struct Key([u8; 32]);
+
+impl Key {
+    /// Derive the key from the encryption password, salted with the iden of the user.
+    pub fn derive(password: &str, user_iden: &str) -> Key {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), user_iden.as_bytes(), PBKDF2_ROUNDS, &mut key);
+        Key(key)
+    }
+
+    /// Encrypt `plaintext` with a random IV, returning the base64 encoded message:
+    /// the version byte `1`, the GCM tag, the IV, then the ciphertext.
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let mut iv = [0u8; IV_LEN];
+        iv.copy_from_slice(&Aes256Gcm::generate_nonce(&mut OsRng));
+        self.encrypt_with_iv(plaintext, &iv)
+    }
+
+    fn encrypt_with_iv(&self, plaintext: &str, iv: &[u8; IV_LEN]) -> String {
+        let cipher = Aes256Gcm::new_from_slice(&self.0).expect("key of 32 bytes");
+        let mut ciphertext = plaintext.as_bytes().to_vec();
+        let tag = cipher.encrypt_in_place_detached(Nonce::from_slice(iv), b"", &mut ciphertext)
+            .expect("message too long to encrypt");
+        let mut message = Vec::with_capacity(1 + TAG_LEN + IV_LEN + ciphertext.len());
+        message.push(VERSION);
+        message.extend_from_slice(&tag);
+        message.extend_from_slice(iv);
+        message.extend_from_slice(&ciphertext);
+        base64::encode(&message)
+    }
+
+    /// Decrypt a base64 encoded message, as sent by the official apps.
+    ///
+    /// Fails if the message was encrypted with another key, e.g. a different password.
+    pub fn decrypt(&self, message: &str) -> Result<String, Box<dyn Error>> {
+        let message = base64::decode(message)?;
+        if message.len() < 1 + TAG_LEN + IV_LEN || message[0] != VERSION {
+            return Err(From::from("unsupported encrypted message"));
+        }
+        let (tag, rest) = message[1..].split_at(TAG_LEN);
+        let (iv, ciphertext) = rest.split_at(IV_LEN);
+        let cipher = Aes256Gcm::new_from_slice(&self.0).expect("key of 32 bytes");
+        let mut plaintext = ciphertext.to_vec();
+        cipher.decrypt_in_place_detached(Nonce::from_slice(iv), b"", &mut plaintext, Tag::from_slice(tag))
+            .map_err(|_| "failed to decrypt message")?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+impl fmt::Debug for Key {
+    /// The key is redacted.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Key(***)")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_test() {
+        let key = Key::derive("hunter2", "ujpah72o0");
+        assert_eq!(base64::encode(key.0), "WwiVLghQfEfaKA/mytTGcshnHlcgDmLOtzRUOUVz/5I=");
+        assert_eq!(format!("{:?}", key), "Key(***)");
+    }
+
+    #[test]
+    fn encrypt_test() {
+        let key = Key::derive("hunter2", "ujpah72o0");
+        let iv = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+        assert_eq!(key.encrypt_with_iv(r#"{"type":"clip","body":"hi"}"#, &iv), ENCRYPTED_CLIP);
+
+        let message = base64::decode(key.encrypt("hi")).unwrap();
+        assert_eq!(message[0], VERSION);
+        assert_eq!(message.len(), 1 + TAG_LEN + IV_LEN + 2);
+        assert_ne!(key.encrypt("hi"), key.encrypt("hi"));
+    }
+
+    #[test]
+    fn decrypt_test() {
+        let key = Key::derive("hunter2", "ujpah72o0");
+        assert_eq!(key.decrypt(ENCRYPTED_CLIP).unwrap(), r#"{"type":"clip","body":"hi"}"#);
+        assert_eq!(key.decrypt(&key.encrypt("hi")).unwrap(), "hi");
+
+        let other = Key::derive("hunter3", "ujpah72o0");
+        assert!(other.decrypt(ENCRYPTED_CLIP).is_err());
+        assert!(key.decrypt("MQ==").is_err());
+        assert!(key.decrypt("not base64").is_err());
+    }
+
+    /// Encrypted with the `cryptography` Python package
+    const ENCRYPTED_CLIP: &str = "MQiUyNBv+t/xPvUGVeBwqoYAAQIDBAUGBwgJCgvA9DBz+sjOhGzOV9SJ9RZvohUmLiFKId4h/CI=";
+}
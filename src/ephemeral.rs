@@ -0,0 +1,100 @@
+//! Pushbullet Ephemerals API
+//!
+//! Ephemerals are short messages delivered to the user's connected devices and not stored.
+//! See [`PushbulletClient::create_ephemeral()`](../struct.PushbulletClient.html#method.create_ephemeral)
+//! or [`PushbulletClient::dismiss_ephemeral()`](../struct.PushbulletClient.html#method.dismiss_ephemeral)
+
+use super::*;
+
+
+const EPHEMERALS_PATH: &str = "ephemerals";
+
+/// Parameters of a mirrored notification to dismiss,
+/// see [`PushbulletClient::dismiss_ephemeral()`](../struct.PushbulletClient.html#method.dismiss_ephemeral)
+#[derive(Debug)]
+pub struct Dismissal<'a> {
+    /// Package name of the Android app that posted the notification, e.g. "com.pushbullet.android"
+    pub package_name: &'a str,
+    /// Android notification id of the notification
+    pub notification_id: &'a str,
+    /// Android notification tag of the notification, if it has one
+    pub notification_tag: Option<&'a str>,
+    /// User iden of the account the notification was mirrored from
+    pub source_user_iden: &'a str,
+}
+
+fn dismissal_json(dismissal: &Dismissal) -> Value {
+    json!({
+        "type": "dismissal",
+        "package_name": dismissal.package_name,
+        "notification_id": dismissal.notification_id,
+        "notification_tag": dismissal.notification_tag,
+        "source_user_iden": dismissal.source_user_iden
+    })
+}
+
+impl PushbulletClient {
+    /// Send an ephemeral. `push` is the inner message, which must have a `type` field.
+    ///
+    /// With an encryption password set, `push` is sent encrypted.
+    pub fn create_ephemeral(&self, push: Value) -> Result<ResponseHeaders, Box<dyn Error>> {
+        #[cfg(feature = "encryption")]
+        let push = match self.encryption_key {
+            Some(ref key) => json!({
+                "encrypted": true,
+                "ciphertext": key.encrypt(&push.to_string())
+            }),
+            None => push
+        };
+        let json = json!({
+            "type": "push",
+            "push": push
+        });
+        debug!("json: {}", json);
+        self.post(&self.api_url(EPHEMERALS_PATH), json).map(|(_, headers)| headers)
+    }
+
+    /// Dismiss a mirrored notification on all of the user's devices.
+    pub fn dismiss_ephemeral(&self, dismissal: &Dismissal) -> Result<ResponseHeaders, Box<dyn Error>> {
+        self.create_ephemeral(dismissal_json(dismissal))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dismissal_json_test() {
+        let dismissal = Dismissal {
+            package_name: "com.pushbullet.android",
+            notification_id: "-8",
+            notification_tag: None,
+            source_user_iden: "ujpah72o0",
+        };
+        assert_eq!(dismissal_json(&dismissal), json!({
+            "type": "dismissal",
+            "package_name": "com.pushbullet.android",
+            "notification_id": "-8",
+            "notification_tag": null,
+            "source_user_iden": "ujpah72o0"
+        }));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn create_encrypted_ephemeral_test() {
+        let (mut server, mut client) = mock_client();
+        client.set_encryption_password("hunter2", "ujpah72o0");
+        let mock = server.mock("POST", "/ephemerals")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "type": "push",
+                "push": {"encrypted": true}
+            })))
+            .with_body("{}")
+            .create();
+        client.create_ephemeral(json!({"type": "clip", "body": "hi"})).unwrap();
+        mock.assert();
+    }
+}
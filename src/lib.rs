@@ -12,19 +12,44 @@ Unofficial [Pushbullet API](https://docs.pushbullet.com/) client.
 This crate has support for a limited set of the APIs.
 
 ## Push API
-* [create-push](struct.PushbulletClient.html#method.create_push) -- except for the file type push
+* [create-push](struct.PushbulletClient.html#method.create_push) -- file type push of an already uploaded file only
 * [list-push](struct.PushbulletClient.html#method.list_push)
 * update-push -- not supported
-* delete-push -- not supported
+* [delete-push](struct.PushbulletClient.html#method.delete_push)
 * delete-all-pushes -- not supported
 
 ## Device API
 * [list-devices](struct.PushbulletClient.html#method.list_devices)
-* create-device -- not supported
+* [create-device](struct.PushbulletClient.html#method.create_device)
 * update-device -- not supported
 * delete-device -- not supported
 
-Chat, Subscription, User, Upload API are not supported.
+## Subscription API
+* [list-subscriptions](struct.PushbulletClient.html#method.list_subscriptions)
+* create-subscription -- not supported
+* update-subscription -- [muted](struct.PushbulletClient.html#method.mute_subscription) only
+* delete-subscription -- not supported
+* [channel-info](struct.PushbulletClient.html#method.get_channel_info)
+* [list owned channels](struct.PushbulletClient.html#method.list_owned_channels)
+
+## User API
+* [users-me](struct.PushbulletClient.html#method.get_user_info)
+
+## Permanents API
+* [get](struct.PushbulletClient.html#method.get_permanent) /
+  [set](struct.PushbulletClient.html#method.set_permanent) a permanent
+* [list SMS threads](struct.PushbulletClient.html#method.list_sms_threads) and
+  [their messages](struct.PushbulletClient.html#method.list_sms_messages)
+
+## Ephemerals API
+* [create-ephemeral](struct.PushbulletClient.html#method.create_ephemeral)
+* [dismissal](struct.PushbulletClient.html#method.dismiss_ephemeral)
+* [end-to-end encryption](struct.PushbulletClient.html#method.set_encryption_password) -- requires the `encryption` feature
+
+## Realtime Event Stream
+* [connect](struct.PushbulletClient.html#method.connect_stream) -- requires the `stream` feature
+
+Chat, Upload API are not supported.
 
 # Examples
 
@@ -51,6 +76,7 @@ See [examples/](examples) directory for more.
 
 #[macro_use]
 extern crate log;
+extern crate base64;
 extern crate chrono;
 extern crate reqwest;
 extern crate serde;
@@ -58,25 +84,51 @@ extern crate serde;
 extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
-#[macro_use]
-extern crate lazy_static;
 
 pub mod push;
 pub mod device;
+pub mod ephemeral;
+pub mod permanent;
+pub mod sms;
+pub mod subscription;
+pub mod user;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 
 use std::error::Error;
-use std::io;
-use std::io::Read;
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 use chrono::prelude::*;
 use serde_json::Value;
 
 
 const BASE_URL: &str = "https://api.pushbullet.com/v2/";
 
+/// Delay before the first retry of a server error; doubled for each further retry
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
 /// Convert unix timestamp in floating point seconds to `DateTime`
+///
+/// This is a few float operations, so the `created_time()` and `modified_time()` helpers
+/// of the response types call it each time instead of caching; `benches/time_conversion.rs`
+/// measures it over 10k pushes.
+///
+/// Panics if `t` is out of the range of `DateTime`; see
+/// [`checked_float_unix_time2date_time()`](fn.checked_float_unix_time2date_time.html).
 pub fn float_unix_time2date_time(t: f64) -> DateTime<Utc> {
+    checked_float_unix_time2date_time(t).expect("timestamp out of range")
+}
+
+/// Convert unix timestamp in floating point seconds to `DateTime`, or `None` if it is
+/// out of range, e.g. for a timestamp from untrusted input.
+pub fn checked_float_unix_time2date_time(t: f64) -> Option<DateTime<Utc>> {
     let nano = t.fract() * 1_000_000_000f64;
-    Utc.timestamp(t.trunc() as i64, nano.round() as u32)
+    Utc.timestamp_opt(t.trunc() as i64, nano.round() as u32).single()
 }
 
 /// Convert `DateTime` to unix timestamp in floating point seconds
@@ -85,7 +137,98 @@ pub fn date_time2float_unix_time(t: DateTime<Utc>) -> f64 {
     unix_time + (t.timestamp_subsec_nanos() as f64 / 1_000_000_000f64)
 }
 
+/// Format `DateTime` as the `modified_after` query parameter, the same way as `list_push()`,
+/// e.g. to build URLs for [`PushbulletClient::get_raw()`](struct.PushbulletClient.html#method.get_raw).
+pub fn format_modified_param(t: DateTime<Utc>) -> String {
+    format_timestamp_param(date_time2float_unix_time(t))
+}
+
+/// Format a timestamp as a query parameter, keeping every digit of precision.
+fn format_timestamp_param(t: f64) -> String {
+    format!("{}", t)
+}
+
+/// Serde support for unix timestamps in floating point seconds as `DateTime<Utc>`
+///
+/// Use with `#[serde(with = "pshbullet_client::float_unix_time")]` on a `DateTime<Utc>` field.
+/// The response types keep the raw `f64` fields so that values such as `modified`
+/// can be passed back to the API exactly.
+pub mod float_unix_time {
+    use chrono::prelude::*;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// Fails on a timestamp out of the range of `DateTime`.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+        where D: Deserializer<'de> {
+        let t = f64::deserialize(deserializer)?;
+        super::checked_float_unix_time2date_time(t)
+            .ok_or_else(|| D::Error::custom(format!("timestamp out of range: {}", t)))
+    }
+
+    pub fn serialize<S>(t: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+        serializer.serialize_f64(super::date_time2float_unix_time(*t))
+    }
+}
+
+/// Error returned by the API client
+///
+/// Returned boxed as `Box<dyn Error>`; use `downcast_ref::<PushbulletError>()` to inspect it.
 #[derive(Debug)]
+pub enum PushbulletError {
+    /// The API responded with a non-success status
+    Http {
+        /// HTTP status of the response
+        status: reqwest::StatusCode,
+        /// Rate limit headers of the response
+        headers: ResponseHeaders,
+        /// Body of the response, with invalid UTF-8 replaced
+        body: String,
+    },
+    /// The API rejected the access token (401 Unauthorized)
+    InvalidToken,
+    /// The request was rejected before being sent
+    InvalidRequest(String),
+    /// The response body is larger than the limit set with `with_max_body_bytes()`
+    BodyTooLarge(u64),
+    /// A file is larger than the `max_upload_size` of the user's account
+    FileTooLarge {
+        /// Size of the file in bytes
+        size: u64,
+        /// `max_upload_size` of the account in bytes
+        max_upload_size: u64,
+    },
+}
+
+impl fmt::Display for PushbulletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PushbulletError::Http { status, .. } => write!(f, "Response has error status: {}", status),
+            PushbulletError::InvalidToken => write!(f, "Invalid access token"),
+            PushbulletError::InvalidRequest(ref reason) => write!(f, "Invalid request: {}", reason),
+            PushbulletError::BodyTooLarge(limit) => write!(f, "Response body exceeds {} bytes", limit),
+            PushbulletError::FileTooLarge { size, max_upload_size } =>
+                write!(f, "File of {} bytes exceeds the maximum upload size of {} bytes", size, max_upload_size)
+        }
+    }
+}
+
+impl Error for PushbulletError {}
+
+impl PushbulletError {
+    /// Time at which the rate limit resets, for an error status response that reported it.
+    ///
+    /// Mostly useful after a 429 (Too Many Requests) response.
+    pub fn retry_after(&self) -> Option<DateTime<Utc>> {
+        match *self {
+            PushbulletError::Http { ref headers, .. } => headers.ratelimit_reset_time(),
+            _ => None
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
 pub struct ResponseHeaders {
     pub ratelimit_limit: Option<i64>,
     pub ratelimit_remaining: Option<i64>,
@@ -94,59 +237,329 @@ pub struct ResponseHeaders {
 
 impl ResponseHeaders {
     pub fn ratelimit_reset_time(&self) -> Option<DateTime<Utc>> {
-        self.ratelimit_reset.map(|sec| Utc.timestamp(sec, 0))
+        self.ratelimit_reset.and_then(|sec| Utc.timestamp_opt(sec, 0).single())
+    }
+
+    /// Block until the rate limit resets, if no requests remain.
+    ///
+    /// Returns immediately if requests remain, the headers are missing, or the
+    /// reset time has already passed.
+    pub fn wait_until_reset(&self) {
+        if let Some(wait) = self.reset_wait(Utc::now()) {
+            info!("rate limit exhausted, waiting {:?} until reset", wait);
+            thread::sleep(wait);
+        }
+    }
+
+    fn reset_wait(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.reset_wait_below(1, now)
+    }
+
+    /// Time until the rate limit resets, if fewer than `threshold` requests remain.
+    fn reset_wait_below(&self, threshold: i64, now: DateTime<Utc>) -> Option<Duration> {
+        match self.ratelimit_remaining {
+            Some(remaining) if remaining < threshold => (),
+            _ => return None
+        }
+        self.ratelimit_reset_time()
+            .and_then(|reset| reset.signed_duration_since(now).to_std().ok())
     }
 }
 
-/// Pushbullet API Client
+/// Measurements of a single HTTP request, passed to the
+/// [`PushbulletClient::on_request()`](struct.PushbulletClient.html#method.on_request) callback
 #[derive(Debug)]
+pub struct RequestMetrics {
+    /// HTTP method of the request
+    pub method: reqwest::Method,
+    /// Requested URL
+    pub url: reqwest::Url,
+    /// Response status, `None` if no response was received (e.g. connection error or timeout)
+    pub status: Option<reqwest::StatusCode>,
+    /// Time until the response headers were received
+    pub duration: Duration,
+}
+
+type RequestCallback = Arc<dyn Fn(&RequestMetrics) + Send + Sync>;
+
+/// Pushbullet API Client
+///
+/// The client is `Send` and `Sync`: it can be shared between threads, or moved into
+/// `tokio::task::spawn_blocking` when used from async code. All requests are blocking.
+///
+/// A blocking request cannot be cancelled: dropping the future of `spawn_blocking`
+/// does not stop it, and it runs until it completes or times out. Bound requests with
+/// [`PushbulletClientBuilder::timeout()`](struct.PushbulletClientBuilder.html#method.timeout)
+/// where they may need to be abandoned.
+///
+/// Cloning is cheap: clones share the connection pool, and settings changed with the
+/// `with_*()` methods afterwards only apply to the clone they are called on.
+#[derive(Clone)]
 pub struct PushbulletClient {
-    access_token: String
+    access_token: String,
+    base_url: String,
+    auth_header: String,
+    compression: bool,
+    max_retries: u32,
+    max_retry_delay: Duration,
+    timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    max_body_bytes: Option<u64>,
+    on_request: Option<RequestCallback>,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<encryption::Key>,
+    /// Built on first use, so that it is built once after all settings are applied
+    http_client: Arc<OnceLock<reqwest::blocking::Client>>,
 }
 
 impl PushbulletClient {
     /// Initialize with an access token.
     pub fn new(access_token: String) -> PushbulletClient {
-        PushbulletClient { access_token }
+        PushbulletClient {
+            access_token,
+            base_url: String::from(BASE_URL),
+            auth_header: String::from("Access-Token"),
+            compression: true,
+            max_retries: 0,
+            max_retry_delay: Duration::from_secs(0),
+            timeout: None,
+            pool_max_idle_per_host: None,
+            max_body_bytes: None,
+            on_request: None,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            http_client: Arc::new(OnceLock::new()),
+        }
     }
 
-    fn get(&self, url: &str)
-            -> Result<(reqwest::blocking::Response, ResponseHeaders), Box<dyn Error>> {
-        debug!("url: {}", url);
-        debug!("access_token: {}", self.access_token);
+    /// Start configuring a client, see [`PushbulletClientBuilder`](struct.PushbulletClientBuilder.html).
+    pub fn builder() -> PushbulletClientBuilder {
+        PushbulletClientBuilder::default()
+    }
 
-        let client = reqwest::blocking::Client::new();
-        let mut response = client.get(url)
-            .header("Access-Token", self.access_token.clone())
-            .send()?;
+    /// Enable or disable gzip response decompression. Enabled by default.
+    ///
+    /// This requires the `gzip` feature of reqwest, which this crate turns on.
+    pub fn with_compression(mut self, enabled: bool) -> PushbulletClient {
+        self.compression = enabled;
+        self.http_client = Arc::new(OnceLock::new());
+        self
+    }
 
-        if response.status().is_success() {
-            debug!("success status: {}", response.status());
-            let raw_headers = response.headers();
-            let response_headers = parse_response_headers(raw_headers);
-            trace!("response_headers: {:?}", response_headers);
-            Ok((response, response_headers))
+    /// Send requests to another API root than `https://api.pushbullet.com/v2/`,
+    /// e.g. a proxy or a mock server in tests.
+    pub fn with_base_url(mut self, base_url: &str) -> PushbulletClient {
+        self.base_url = base_url.to_owned();
+        if !self.base_url.ends_with('/') {
+            self.base_url.push('/');
+        }
+        self
+    }
+
+    /// Retry requests that fail with a server error (5xx status), such as a 503
+    /// during a Pushbullet deploy. Disabled by default.
+    ///
+    /// Requests are retried up to `max_retries` times with exponential backoff,
+    /// starting at 500ms and never waiting longer than `max_delay` between attempts.
+    /// Rate limiting (429) is not retried.
+    ///
+    /// Only GET and DELETE requests are retried. A POST that failed with a server error
+    /// may still have been applied, e.g. created a push, so it is sent only once; use
+    /// [`create_push_idempotent()`](#method.create_push_idempotent) to retry pushes safely.
+    pub fn with_retry(mut self, max_retries: u32, max_delay: Duration) -> PushbulletClient {
+        self.max_retries = max_retries;
+        self.max_retry_delay = max_delay;
+        self
+    }
+
+    /// Keep at most `max` idle connections per host in the connection pool, e.g. to tune
+    /// a client shared by many threads. Unlimited by default.
+    ///
+    /// This sets `pool_max_idle_per_host` of the underlying reqwest client builder.
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> PushbulletClient {
+        self.pool_max_idle_per_host = Some(max);
+        self.http_client = Arc::new(OnceLock::new());
+        self
+    }
+
+    /// Fail requests whose response body is larger than `max` bytes, instead of reading it
+    /// into memory. Unlimited by default.
+    ///
+    /// A response is rejected up front if its `Content-Length` exceeds the limit. Otherwise
+    /// the body is read into memory, failing once more than `max` bytes have been read,
+    /// before it is parsed. Both fail with `PushbulletError::BodyTooLarge`. Files downloaded
+    /// with [`download_file()`](#method.download_file) are not limited.
+    pub fn with_max_body_bytes(mut self, max: u64) -> PushbulletClient {
+        self.max_body_bytes = Some(max);
+        self
+    }
+
+    fn retry_delay(&self, retry: u32) -> Duration {
+        RETRY_BASE_DELAY
+            .checked_mul(1 << (retry - 1).min(16))
+            .map_or(self.max_retry_delay, |delay| delay.min(self.max_retry_delay))
+    }
+
+    /// Send the access token in a different header, e.g. for an API gateway. Defaults to `Access-Token`.
+    ///
+    /// With `Authorization` the token is sent as `Bearer {token}`; with any other
+    /// header the bare token is sent.
+    pub fn with_auth_header(mut self, name: &str) -> PushbulletClient {
+        self.auth_header = name.to_owned();
+        self
+    }
+
+    /// Call `callback` after each HTTP request, including retries, e.g. to record metrics.
+    pub fn on_request<F>(mut self, callback: F) -> PushbulletClient
+        where F: Fn(&RequestMetrics) + Send + Sync + 'static {
+        self.on_request = Some(Arc::new(callback));
+        self
+    }
+
+    /// Encrypt ephemerals sent with `create_ephemeral()` end-to-end, like the official apps
+    /// do once an encryption password is set in their settings.
+    ///
+    /// The key is derived from `password` and `user_iden`, the iden of the current user,
+    /// which must be the same on all devices. Requires the `encryption` feature.
+    #[cfg(feature = "encryption")]
+    pub fn set_encryption_password(&mut self, password: &str, user_iden: &str) {
+        self.encryption_key = Some(encryption::Key::derive(password, user_iden));
+    }
+
+    fn api_url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn auth_header_value(&self) -> String {
+        if self.auth_header.eq_ignore_ascii_case("Authorization") {
+            format!("Bearer {}", self.access_token)
         } else {
-            error!("error status: {:?}", response);
-            let mut buf = String::new();
-            if response.read_to_string(&mut buf).is_ok() {
-                error!("error response body: {}", buf)
-            }
-            Err(From::from(io::Error::new(io::ErrorKind::Other, "Response has error status")))
+            self.access_token.clone()
+        }
+    }
+
+    fn http_client(&self) -> &reqwest::blocking::Client {
+        self.http_client.get_or_init(|| self.build_http_client())
+    }
+
+    fn build_http_client(&self) -> reqwest::blocking::Client {
+        self.http_client_builder()
+            .build()
+            .expect("failed to build HTTP client")
+    }
+
+    fn http_client_builder(&self) -> reqwest::blocking::ClientBuilder {
+        let mut builder = reqwest::blocking::Client::builder()
+            .gzip(self.compression);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
         }
+        builder
+    }
+
+    /// Low-level escape hatch: authenticated GET of an API path, returning the JSON body.
+    ///
+    /// `path` is relative to the API root, e.g. `"users/me"` for
+    /// `https://api.pushbullet.com/v2/users/me`. Prefer the typed methods where they exist.
+    pub fn get_raw(&self, path: &str) -> Result<Value, Box<dyn Error>> {
+        let url = self.api_url(path);
+        let (raw_response, _) = self.get(&url)?;
+        Ok(serde_json::from_reader(raw_response)?)
+    }
+
+    fn get(&self, url: &str)
+            -> Result<(Body, ResponseHeaders), Box<dyn Error>> {
+        debug!("url: {}", url);
+        self.send(self.http_client().get(url))
     }
 
     fn post(&self, url: &str, json: Value)
-            -> Result<(reqwest::blocking::Response, ResponseHeaders), Box<dyn Error>> {
+            -> Result<(Body, ResponseHeaders), Box<dyn Error>> {
         debug!("url: {}", url);
-        debug!("access_token: {}", self.access_token);
+        self.send(self.http_client().post(url).json(&json))
+    }
+
+    fn delete(&self, url: &str)
+              -> Result<(Body, ResponseHeaders), Box<dyn Error>> {
+        debug!("url: {}", url);
+        self.send(self.http_client().delete(url))
+    }
+
+    fn send(&self, request: reqwest::blocking::RequestBuilder)
+            -> Result<(Body, ResponseHeaders), Box<dyn Error>> {
+        self.execute(self.authorize(request))
+    }
+
+    fn authorize(&self, request: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+        request.header(self.auth_header.as_str(), self.auth_header_value())
+    }
+
+    /// Send a request as is, without the access token
+    fn execute(&self, request: reqwest::blocking::RequestBuilder)
+               -> Result<(Body, ResponseHeaders), Box<dyn Error>> {
+        let (response, response_headers) = self.execute_response(request)?;
+        Ok((self.limit_body(response)?, response_headers))
+    }
 
-        let client = reqwest::blocking::Client::new();
-        let mut response = client.post(url)
-            .header("Access-Token", self.access_token.clone())
-            .json(&json)
-            .send()?;
+    /// Read the body into memory if it is limited, so that a body that is too large
+    /// fails here with `PushbulletError::BodyTooLarge` rather than while it is parsed.
+    fn limit_body(&self, response: reqwest::blocking::Response) -> Result<Body, Box<dyn Error>> {
+        let limit = match self.max_body_bytes {
+            Some(limit) => limit,
+            None => return Ok(Body::Streaming(Box::new(response)))
+        };
+        if let Some(length) = response.content_length() {
+            if length > limit {
+                error!("response body of {} bytes exceeds limit", length);
+                return Err(From::from(PushbulletError::BodyTooLarge(limit)));
+            }
+        }
+        // read up to one byte past the limit to notice a body that is too large
+        let mut buf = vec![];
+        response.take(limit.saturating_add(1)).read_to_end(&mut buf)?;
+        if buf.len() as u64 > limit {
+            error!("response body exceeds limit of {} bytes", limit);
+            return Err(From::from(PushbulletError::BodyTooLarge(limit)));
+        }
+        Ok(Body::Buffered(io::Cursor::new(buf)))
+    }
+
+    /// Send a request as is, returning the response of a success status with its body unread
+    fn execute_response(&self, request: reqwest::blocking::RequestBuilder)
+                        -> Result<(reqwest::blocking::Response, ResponseHeaders), Box<dyn Error>> {
+        let response = self.execute_with_retry(request)?;
+        self.check_status(response)
+    }
 
+    /// Send a request as is, retrying it on a server error status as set up with `with_retry()`
+    fn execute_with_retry(&self, request: reqwest::blocking::RequestBuilder)
+                          -> Result<reqwest::blocking::Response, Box<dyn Error>> {
+        let request = request.build()?;
+        let max_retries = if is_idempotent(request.method()) { self.max_retries } else { 0 };
+        let mut retries = 0;
+        let response = loop {
+            let response = match request.try_clone() {
+                Some(attempt) if retries < max_retries => self.execute_once(attempt)?,
+                _ => break self.execute_once(request)?
+            };
+            if !response.status().is_server_error() {
+                break response;
+            }
+            retries += 1;
+            let delay = self.retry_delay(retries);
+            warn!("server error status: {}, retrying in {:?} ({}/{})",
+                  response.status(), delay, retries, max_retries);
+            thread::sleep(delay);
+        };
+        Ok(response)
+    }
+
+    /// Turn a non-success status into an error, keeping the body of a success status unread
+    fn check_status(&self, mut response: reqwest::blocking::Response)
+                    -> Result<(reqwest::blocking::Response, ResponseHeaders), Box<dyn Error>> {
         if response.status().is_success() {
             debug!("success status: {}", response.status());
             let raw_headers = response.headers();
@@ -155,47 +568,279 @@ impl PushbulletClient {
             Ok((response, response_headers))
         } else {
             error!("error status: {:?}", response);
-            let mut buf = String::new();
-            if response.read_to_string(&mut buf).is_ok() {
-                error!("error response body: {}", buf)
+            let status = response.status();
+            let headers = parse_response_headers(response.headers());
+            let mut buf = vec![];
+            let limit = self.max_body_bytes.unwrap_or(u64::MAX);
+            if let Err(e) = response.by_ref().take(limit).read_to_end(&mut buf) {
+                error!("failed to read error response body: {}", e)
             }
-            Err(From::from(io::Error::new(io::ErrorKind::Other, "Response has error status")))
+            let body = String::from_utf8_lossy(&buf).into_owned();
+            error!("error response body: {}", body);
+            if status == reqwest::StatusCode::UNAUTHORIZED {
+                Err(From::from(PushbulletError::InvalidToken))
+            } else {
+                Err(From::from(PushbulletError::Http { status, headers, body }))
+            }
+        }
+    }
+
+    fn execute_once(&self, request: reqwest::blocking::Request) -> reqwest::Result<reqwest::blocking::Response> {
+        let method = request.method().clone();
+        let url = request.url().clone();
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("pushbullet_request",
+                                        method = %method, url = %url, status = tracing::field::Empty);
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let start = Instant::now();
+        let result = self.http_client().execute(request);
+        let status = result.as_ref().ok().map(|response| response.status());
+        #[cfg(feature = "tracing")]
+        if let Some(status) = status {
+            span.record("status", status.as_u16());
+        }
+        if let Some(ref callback) = self.on_request {
+            callback(&RequestMetrics { method, url, status, duration: start.elapsed() });
         }
+        result
     }
 }
 
-fn parse_response_headers(headers: &reqwest::header::HeaderMap) -> ResponseHeaders {
-    let ratelimit_limit = headers
-        .get("X-Ratelimit-Limit")
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .parse::<i64>()
-        .unwrap();
-
-    let ratelimit_remaining = headers
-        .get("X-Ratelimit-Remaining")
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .parse::<i64>()
-        .unwrap();
-
-    let ratelimit_reset = headers
-        .get("X-Ratelimit-Reset")
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .parse::<i64>()
-        .unwrap();
+/// Whether a request can be retried after a server error without being applied twice
+fn is_idempotent(method: &reqwest::Method) -> bool {
+    *method == reqwest::Method::GET || *method == reqwest::Method::DELETE
+}
+
+impl fmt::Debug for PushbulletClient {
+    /// The access token is redacted.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut d = f.debug_struct("PushbulletClient");
+        d.field("access_token", &"***")
+            .field("base_url", &self.base_url)
+            .field("auth_header", &self.auth_header)
+            .field("compression", &self.compression)
+            .field("max_retries", &self.max_retries)
+            .field("max_retry_delay", &self.max_retry_delay)
+            .field("timeout", &self.timeout)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host)
+            .field("max_body_bytes", &self.max_body_bytes)
+            .field("on_request", &self.on_request.as_ref().map(|_| "Fn(&RequestMetrics)"));
+        #[cfg(feature = "encryption")]
+        d.field("encryption_key", &self.encryption_key);
+        d.finish()
+    }
+}
 
+/// Body of a successful response: streamed, or already read into memory when the
+/// client limits body sizes.
+enum Body {
+    Streaming(Box<reqwest::blocking::Response>),
+    Buffered(io::Cursor<Vec<u8>>),
+}
+
+impl Read for Body {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Body::Streaming(ref mut response) => response.read(buf),
+            Body::Buffered(ref mut cursor) => cursor.read(buf)
+        }
+    }
+}
+
+/// Builder of a [`PushbulletClient`](struct.PushbulletClient.html) with all of its settings.
+///
+/// ```
+/// # use pshbullet_client::PushbulletClient;
+/// # use std::time::Duration;
+/// let client = PushbulletClient::builder()
+///     .token("<your_access_token>")
+///     .timeout(Duration::from_secs(10))
+///     .retry(3, Duration::from_secs(5))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct PushbulletClientBuilder {
+    access_token: Option<String>,
+    base_url: Option<String>,
+    auth_header: Option<String>,
+    timeout: Option<Duration>,
+    retry: Option<(u32, Duration)>,
+    max_body_bytes: Option<u64>,
+}
+
+impl PushbulletClientBuilder {
+    /// Access token of the user. Required.
+    pub fn token(mut self, access_token: &str) -> PushbulletClientBuilder {
+        self.access_token = Some(access_token.to_owned());
+        self
+    }
+
+    /// See [`PushbulletClient::with_base_url()`](struct.PushbulletClient.html#method.with_base_url)
+    pub fn base_url(mut self, base_url: &str) -> PushbulletClientBuilder {
+        self.base_url = Some(base_url.to_owned());
+        self
+    }
+
+    /// Timeout of each request, from connecting until the response body is read.
+    /// Defaults to reqwest's 30 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> PushbulletClientBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// See [`PushbulletClient::with_retry()`](struct.PushbulletClient.html#method.with_retry)
+    pub fn retry(mut self, max_retries: u32, max_delay: Duration) -> PushbulletClientBuilder {
+        self.retry = Some((max_retries, max_delay));
+        self
+    }
+
+    /// See [`PushbulletClient::with_max_body_bytes()`](struct.PushbulletClient.html#method.with_max_body_bytes)
+    pub fn max_body_bytes(mut self, max: u64) -> PushbulletClientBuilder {
+        self.max_body_bytes = Some(max);
+        self
+    }
+
+    /// See [`PushbulletClient::with_auth_header()`](struct.PushbulletClient.html#method.with_auth_header)
+    pub fn auth_header(mut self, name: &str) -> PushbulletClientBuilder {
+        self.auth_header = Some(name.to_owned());
+        self
+    }
+
+    /// Build the client. Fails with `PushbulletError::InvalidRequest` if no token was set.
+    pub fn build(self) -> Result<PushbulletClient, Box<dyn Error>> {
+        let access_token = match self.access_token {
+            Some(access_token) => access_token,
+            None => return Err(From::from(PushbulletError::InvalidRequest(String::from("access token is not set"))))
+        };
+        let mut client = PushbulletClient::new(access_token);
+        if let Some(base_url) = self.base_url {
+            client = client.with_base_url(&base_url);
+        }
+        if let Some(auth_header) = self.auth_header {
+            client = client.with_auth_header(&auth_header);
+        }
+        if let Some((max_retries, max_delay)) = self.retry {
+            client = client.with_retry(max_retries, max_delay);
+        }
+        if let Some(max) = self.max_body_bytes {
+            client = client.with_max_body_bytes(max);
+        }
+        client.timeout = self.timeout;
+        Ok(client)
+    }
+}
+
+/// Devices and recent pushes of the user, see
+/// [`PushbulletClient::fetch_overview()`](struct.PushbulletClient.html#method.fetch_overview)
+#[derive(Debug)]
+pub struct Overview {
+    /// Result of `list_devices()`
+    pub devices: device::Result,
+    /// Result of `list_push()`
+    pub pushes: Result<(Vec<push::Response>, ResponseHeaders), Box<dyn Error>>,
+}
+
+impl PushbulletClient {
+    /// Fetch the devices and the `push_limit` most recent active pushes in parallel,
+    /// e.g. for the initial load of a dashboard.
+    ///
+    /// Both requests are always made, so one part can succeed while the other fails.
+    pub fn fetch_overview(&self, push_limit: u32) -> Overview {
+        thread::scope(|scope| {
+            let devices = scope.spawn(|| self.list_devices().map_err(into_send_error));
+            let pushes = self.list_push(&push::ListCondition::new(push_limit));
+            let devices = devices.join().unwrap_or_else(|panic| ::std::panic::resume_unwind(panic));
+            Overview { devices: devices.map_err(|e| e as Box<dyn Error>), pushes }
+        })
+    }
+}
+
+/// Make an error of this crate `Send`, so that it can be returned from another thread.
+///
+/// The errors of this crate are all `Send`; anything else is kept as its message only.
+fn into_send_error(e: Box<dyn Error>) -> Box<dyn Error + Send + Sync> {
+    let e = match e.downcast::<PushbulletError>() {
+        Ok(e) => return e,
+        Err(e) => e
+    };
+    let e = match e.downcast::<reqwest::Error>() {
+        Ok(e) => return e,
+        Err(e) => e
+    };
+    let e = match e.downcast::<serde_json::Error>() {
+        Ok(e) => return e,
+        Err(e) => e
+    };
+    match e.downcast::<::std::io::Error>() {
+        Ok(e) => e,
+        Err(e) => From::from(e.to_string())
+    }
+}
+
+/// The public API of [`PushbulletClient`](struct.PushbulletClient.html) as a trait.
+///
+/// Code written against `&dyn PushbulletApi` can be given a fake implementation in tests.
+pub trait PushbulletApi {
+    /// See [`PushbulletClient::create_push()`](struct.PushbulletClient.html#method.create_push)
+    fn create_push(&self, target: &push::Target, request: push::Request) -> push::Result;
+
+    /// See [`PushbulletClient::create_push_multi()`](struct.PushbulletClient.html#method.create_push_multi)
+    fn create_push_multi(&self, targets: &[push::Target], request: &push::Request) -> Vec<push::Result>;
+
+    /// See [`PushbulletClient::list_push()`](struct.PushbulletClient.html#method.list_push)
+    fn list_push(&self, condition: &push::ListCondition)
+                 -> Result<(Vec<push::Response>, ResponseHeaders), Box<dyn Error>>;
+
+    /// See [`PushbulletClient::list_devices()`](struct.PushbulletClient.html#method.list_devices)
+    fn list_devices(&self) -> device::Result;
+}
+
+impl PushbulletApi for PushbulletClient {
+    fn create_push(&self, target: &push::Target, request: push::Request) -> push::Result {
+        PushbulletClient::create_push(self, target, request)
+    }
+
+    fn create_push_multi(&self, targets: &[push::Target], request: &push::Request) -> Vec<push::Result> {
+        PushbulletClient::create_push_multi(self, targets, request)
+    }
+
+    fn list_push(&self, condition: &push::ListCondition)
+                 -> Result<(Vec<push::Response>, ResponseHeaders), Box<dyn Error>> {
+        PushbulletClient::list_push(self, condition)
+    }
+
+    fn list_devices(&self) -> device::Result {
+        PushbulletClient::list_devices(self)
+    }
+}
+
+fn parse_response_headers(headers: &reqwest::header::HeaderMap) -> ResponseHeaders {
     ResponseHeaders {
-        ratelimit_limit: Some(ratelimit_limit),
-        ratelimit_remaining: Some(ratelimit_remaining),
-        ratelimit_reset: Some(ratelimit_reset)
+        ratelimit_limit: parse_header(headers, "X-Ratelimit-Limit"),
+        ratelimit_remaining: parse_header(headers, "X-Ratelimit-Remaining"),
+        ratelimit_reset: parse_header(headers, "X-Ratelimit-Reset")
     }
 }
 
+/// Parse a numeric header, `None` if it is missing or malformed
+fn parse_header(headers: &reqwest::header::HeaderMap, name: &str) -> Option<i64> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+}
+
+/// Mock server and a client sending its requests there
+#[cfg(test)]
+fn mock_client() -> (mockito::ServerGuard, PushbulletClient) {
+    let server = mockito::Server::new();
+    let client = PushbulletClient::new(String::from("token")).with_base_url(&server.url());
+    (server, client)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -209,7 +854,367 @@ mod tests {
         };
 
         let reset = headers.ratelimit_reset_time().unwrap();
-        assert_eq!(reset, Utc.ymd(2017, 6, 7).and_hms(17, 30, 53));
+        assert_eq!(reset, Utc.with_ymd_and_hms(2017, 6, 7, 17, 30, 53).unwrap());
+    }
+
+    #[test]
+    fn reset_wait_test() {
+        let mut headers = ResponseHeaders {
+            ratelimit_limit: Some(16384),
+            ratelimit_remaining: Some(0),
+            ratelimit_reset: Some(1496856653)
+        };
+        let reset = headers.ratelimit_reset_time().unwrap();
+        assert_eq!(headers.reset_wait(reset - chrono::Duration::seconds(5)), Some(Duration::from_secs(5)));
+        assert_eq!(headers.reset_wait(reset + chrono::Duration::seconds(5)), None);
+
+        headers.ratelimit_remaining = Some(1);
+        assert_eq!(headers.reset_wait(reset - chrono::Duration::seconds(5)), None);
+        assert_eq!(headers.reset_wait_below(2, reset - chrono::Duration::seconds(5)), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn serialize_response_headers_test() {
+        let headers = ResponseHeaders {
+            ratelimit_limit: Some(16384),
+            ratelimit_remaining: None,
+            ratelimit_reset: Some(1496856653)
+        };
+        assert_eq!(serde_json::to_value(headers.clone()).unwrap(), json!({
+            "ratelimit_limit": 16384,
+            "ratelimit_remaining": null,
+            "ratelimit_reset": 1496856653
+        }));
+    }
+
+    #[test]
+    fn api_trait_object_test() {
+        struct NoDevices;
+        impl PushbulletApi for NoDevices {
+            fn create_push(&self, _: &push::Target, _: push::Request) -> push::Result {
+                Err(From::from("not implemented"))
+            }
+            fn create_push_multi(&self, targets: &[push::Target], request: &push::Request) -> Vec<push::Result> {
+                targets.iter().map(|t| self.create_push(t, request.clone())).collect()
+            }
+            fn list_push(&self, _: &push::ListCondition)
+                         -> Result<(Vec<push::Response>, ResponseHeaders), Box<dyn Error>> {
+                Err(From::from("not implemented"))
+            }
+            fn list_devices(&self) -> device::Result {
+                Ok((vec![], ResponseHeaders {
+                    ratelimit_limit: None,
+                    ratelimit_remaining: None,
+                    ratelimit_reset: None
+                }))
+            }
+        }
+
+        let apis: Vec<Box<dyn PushbulletApi>> = vec![
+            Box::new(NoDevices),
+            Box::new(PushbulletClient::new(String::from("token"))),
+        ];
+        let (devices, _) = apis[0].list_devices().unwrap();
+        assert!(devices.is_empty());
+    }
+
+    #[test]
+    fn debug_redacts_token_test() {
+        let client = PushbulletClient::new(String::from("o.secret-token"));
+        let debug = format!("{:?}", client);
+        assert!(!debug.contains("o.secret-token"));
+        assert!(debug.contains(r#"access_token: "***""#));
+    }
+
+    #[test]
+    fn clone_test() {
+        let client = PushbulletClient::new(String::from("token"))
+            .with_retry(2, Duration::from_secs(1));
+        let clone = client.clone().with_auth_header("Authorization");
+        assert_eq!(clone.max_retries, 2);
+        assert_eq!(clone.auth_header_value(), "Bearer token");
+        assert_eq!(client.auth_header_value(), "token");
+    }
+
+    #[test]
+    fn pool_max_idle_per_host_test() {
+        use std::io::Write;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let mut stream = stream.unwrap();
+                // answer every read, which holds a whole request for these small GETs
+                thread::spawn(move || {
+                    let mut buf = [0; 4096];
+                    while let Ok(n) = stream.read(&mut buf) {
+                        if n == 0 || stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}").is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        // without idle connections in the pool, each request opens a new connection
+        let client = PushbulletClient::new(String::from("token"))
+            .with_base_url(&url)
+            .with_pool_max_idle_per_host(0);
+        client.get_raw("users/me").unwrap();
+        client.get_raw("users/me").unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn http_client_built_on_first_use_test() {
+        let client = PushbulletClient::new(String::from("token"))
+            .with_compression(false)
+            .with_pool_max_idle_per_host(8);
+        assert!(client.http_client.get().is_none());
+        let clone = client.clone();
+        client.http_client();
+        assert!(clone.http_client.get().is_some());
+        let client = client.with_compression(true);
+        assert!(client.http_client.get().is_none());
+    }
+
+    #[test]
+    fn max_body_bytes_test() {
+        let (mut server, client) = mock_client();
+        let client = client.with_max_body_bytes(16);
+        server.mock("GET", "/small")
+            .with_body(r#"{"a": 1}"#)
+            .create();
+        server.mock("GET", "/large")
+            .with_body(r#"{"a": "0123456789abcdef"}"#)
+            .create();
+        server.mock("GET", "/chunked")
+            .with_chunked_body(|w| w.write_all(br#"{"a": "0123456789abcdef"}"#))
+            .create();
+
+        assert_eq!(client.get_raw("small").unwrap(), json!({"a": 1}));
+        let e = client.get_raw("large").unwrap_err();
+        assert!(matches!(e.downcast_ref::<PushbulletError>(), Some(PushbulletError::BodyTooLarge(16))));
+        let e = client.get_raw("chunked").unwrap_err();
+        assert!(matches!(e.downcast_ref::<PushbulletError>(), Some(PushbulletError::BodyTooLarge(16))));
+    }
+
+    #[test]
+    fn builder_test() {
+        let client = PushbulletClient::builder()
+            .token("o.token")
+            .base_url("http://localhost:1234")
+            .auth_header("Authorization")
+            .timeout(Duration::from_secs(10))
+            .retry(2, Duration::from_secs(1))
+            .build()
+            .unwrap();
+        assert_eq!(client.api_url("pushes"), "http://localhost:1234/pushes");
+        assert_eq!(client.auth_header_value(), "Bearer o.token");
+        assert_eq!(client.timeout, Some(Duration::from_secs(10)));
+        assert_eq!(client.max_retries, 2);
+
+        let e = PushbulletClient::builder().build().unwrap_err();
+        assert!(matches!(e.downcast_ref::<PushbulletError>(), Some(PushbulletError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn auth_header_test() {
+        let client = PushbulletClient::new(String::from("o.token"));
+        assert_eq!(client.auth_header, "Access-Token");
+        assert_eq!(client.auth_header_value(), "o.token");
+
+        let client = client.with_auth_header("Authorization");
+        assert_eq!(client.auth_header_value(), "Bearer o.token");
+
+        let client = client.with_auth_header("X-Api-Key");
+        assert_eq!(client.auth_header_value(), "o.token");
+    }
+
+    #[test]
+    fn client_is_send_and_sync_test() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+        assert_send::<PushbulletClient>();
+        assert_sync::<PushbulletClient>();
+    }
+
+    #[test]
+    fn float_unix_time_serde_test() {
+        #[derive(Serialize, Deserialize)]
+        struct Item {
+            #[serde(with = "float_unix_time")]
+            modified: DateTime<Utc>,
+        }
+
+        let item: Item = serde_json::from_str(r#"{"modified": 1496856653.5}"#).unwrap();
+        assert_eq!(item.modified, Utc.with_ymd_and_hms(2017, 6, 7, 17, 30, 53).unwrap() + chrono::Duration::milliseconds(500));
+        assert_eq!(serde_json::to_string(&item).unwrap(), r#"{"modified":1496856653.5}"#);
+
+        let e = serde_json::from_str::<Item>(r#"{"modified": 1e20}"#).err().unwrap();
+        assert!(e.to_string().contains("timestamp out of range"), "{}", e);
+        assert_eq!(checked_float_unix_time2date_time(1e20), None);
+    }
+
+    #[test]
+    fn parse_response_headers_test() {
+        let mut raw_headers = reqwest::header::HeaderMap::new();
+        raw_headers.insert("X-Ratelimit-Limit", "16384".parse().unwrap());
+        raw_headers.insert("X-Ratelimit-Remaining", "oops".parse().unwrap());
+        raw_headers.insert("X-Ratelimit-Reset", "1496856653".parse().unwrap());
+
+        let headers = parse_response_headers(&raw_headers);
+        assert_eq!(headers.ratelimit_limit, Some(16384));
+        assert_eq!(headers.ratelimit_remaining, None);
+        assert_eq!(headers.ratelimit_reset, Some(1496856653));
+
+        let headers = parse_response_headers(&reqwest::header::HeaderMap::new());
+        assert_eq!(headers.ratelimit_limit, None);
+    }
+
+    #[test]
+    fn invalid_token_test() {
+        let (mut server, client) = mock_client();
+        let mock = server.mock("GET", "/users/me")
+            .match_header("Access-Token", "token")
+            .with_status(401)
+            .with_body(r#"{"error": {"type": "invalid_request", "message": "Access token is missing or invalid."}}"#)
+            .expect(2)
+            .create();
+
+        let e = client.get_raw("users/me").unwrap_err();
+        match e.downcast_ref::<PushbulletError>() {
+            Some(PushbulletError::InvalidToken) => (),
+            e => panic!("unexpected error: {:?}", e)
+        }
+        assert!(!client.verify_token().unwrap());
+        mock.assert();
+    }
+
+    #[test]
+    fn non_utf8_error_body_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/users/me")
+            .with_status(400)
+            .with_body([b'b', b'a', b'd', 0xff, 0xfe])
+            .create();
+
+        let e = client.get_raw("users/me").unwrap_err();
+        match e.downcast_ref::<PushbulletError>() {
+            Some(PushbulletError::Http { status, body, .. }) => {
+                assert_eq!(*status, reqwest::StatusCode::BAD_REQUEST);
+                assert_eq!(body, "bad\u{fffd}\u{fffd}");
+            }
+            e => panic!("unexpected error: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn retry_server_error_test() {
+        let (mut server, client) = mock_client();
+        let unavailable = server.mock("GET", "/users/me")
+            .with_status(503)
+            .expect(2)
+            .create();
+        let ok = server.mock("GET", "/users/me")
+            .with_body("{}")
+            .create();
+        let client = client
+            .with_retry(3, Duration::from_millis(10));
+
+        assert_eq!(client.get_raw("users/me").unwrap(), json!({}));
+        unavailable.assert();
+        ok.assert();
+    }
+
+    #[test]
+    fn retry_skips_post_test() {
+        let (mut server, client) = mock_client();
+        let unavailable = server.mock("POST", "/pushes")
+            .with_status(503)
+            .expect(1)
+            .create();
+        let client = client
+            .with_retry(3, Duration::from_millis(10));
+
+        let url = client.api_url("pushes");
+        let e = client.post(&url, json!({"type": "note"})).err().unwrap();
+        match e.downcast_ref::<PushbulletError>() {
+            Some(PushbulletError::Http { status, .. }) => assert_eq!(status.as_u16(), 503),
+            other => panic!("unexpected error: {:?}", other)
+        }
+        unavailable.assert();
+    }
+
+    #[test]
+    fn on_request_test() {
+        let (mut server, client) = mock_client();
+        let _unavailable = server.mock("GET", "/users/me")
+            .with_status(503)
+            .expect(1)
+            .create();
+        let _ok = server.mock("GET", "/users/me")
+            .with_body("{}")
+            .create();
+        let statuses = Arc::new(std::sync::Mutex::new(vec![]));
+        let recorded = statuses.clone();
+        let client = client
+            .with_retry(1, Duration::from_millis(10))
+            .on_request(move |metrics| {
+                assert_eq!(metrics.method, reqwest::Method::GET);
+                assert!(metrics.url.as_str().ends_with("/users/me"));
+                recorded.lock().unwrap().push(metrics.status.unwrap().as_u16());
+            });
+
+        client.get_raw("users/me").unwrap();
+        assert_eq!(*statuses.lock().unwrap(), vec![503, 200]);
+    }
+
+    #[test]
+    fn fetch_overview_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/devices")
+            .with_status(503)
+            .create();
+        server.mock("GET", "/pushes?active=true&limit=5")
+            .with_body(r#"{"pushes": []}"#)
+            .create();
+
+        let overview = client.fetch_overview(5);
+        let e = overview.devices.unwrap_err();
+        assert!(matches!(e.downcast_ref::<PushbulletError>(), Some(PushbulletError::Http { .. })));
+        assert!(overview.pushes.unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn retry_delay_test() {
+        let client = PushbulletClient::new(String::from("token"))
+            .with_retry(40, Duration::from_secs(3));
+        assert_eq!(client.retry_delay(1), Duration::from_millis(500));
+        assert_eq!(client.retry_delay(2), Duration::from_millis(1000));
+        assert_eq!(client.retry_delay(3), Duration::from_millis(2000));
+        assert_eq!(client.retry_delay(4), Duration::from_secs(3));
+        assert_eq!(client.retry_delay(40), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn retry_after_test() {
+        let e = PushbulletError::Http {
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+            headers: ResponseHeaders {
+                ratelimit_limit: Some(16384),
+                ratelimit_remaining: Some(0),
+                ratelimit_reset: Some(1496856653)
+            },
+            body: String::new()
+        };
+        assert_eq!(e.retry_after(), Some(Utc.with_ymd_and_hms(2017, 6, 7, 17, 30, 53).unwrap()));
+        assert_eq!(PushbulletError::InvalidRequest(String::new()).retry_after(), None);
     }
 
     #[test]
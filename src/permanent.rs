@@ -0,0 +1,57 @@
+//! Pushbullet Permanents API
+//!
+//! Per-client key-value storage.
+//! See [`PushbulletClient::get_permanent()`](../struct.PushbulletClient.html#method.get_permanent)
+//! or [`PushbulletClient::set_permanent()`](../struct.PushbulletClient.html#method.set_permanent)
+
+use super::*;
+use reqwest::Url;
+
+
+const PERMANENTS_PATH: &str = "permanents";
+
+impl PushbulletClient {
+    /// Get the value stored under `key`.
+    pub fn get_permanent(&self, key: &str) -> Result<Value, Box<dyn Error>> {
+        let (raw_response, _) = self.get(&self.permanent_url(key)?)?;
+        Ok(serde_json::from_reader(raw_response)?)
+    }
+
+    /// Store `value` under `key`.
+    pub fn set_permanent(&self, key: &str, value: Value) -> Result<ResponseHeaders, Box<dyn Error>> {
+        self.post(&self.permanent_url(key)?, value).map(|(_, headers)| headers)
+    }
+
+    /// URL of a permanent, with `key` escaped as a single path segment
+    fn permanent_url(&self, key: &str) -> Result<String, Box<dyn Error>> {
+        let mut url = Url::parse(&self.api_url(PERMANENTS_PATH))?;
+        url.path_segments_mut()
+            .map_err(|()| PushbulletError::InvalidRequest(format!("base URL cannot have a path: {}", self.base_url)))?
+            .push(key);
+        Ok(String::from(url))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permanent_url_test() {
+        let client = PushbulletClient::new(String::from("token"));
+        assert_eq!(client.permanent_url("ujpah72o0_threads").unwrap(),
+                   "https://api.pushbullet.com/v2/permanents/ujpah72o0_threads");
+        assert_eq!(client.permanent_url("a/b c").unwrap(),
+                   "https://api.pushbullet.com/v2/permanents/a%2Fb%20c");
+    }
+
+    #[test]
+    fn invalid_base_url_test() {
+        let client = PushbulletClient::new(String::from("token")).with_base_url("not a url");
+        assert!(client.get_permanent("key").is_err());
+        let client = PushbulletClient::new(String::from("token")).with_base_url("mailto:x");
+        let e = client.get_permanent("key").unwrap_err();
+        assert!(matches!(e.downcast_ref::<PushbulletError>(), Some(PushbulletError::InvalidRequest(_))));
+    }
+}
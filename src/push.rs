@@ -5,11 +5,44 @@
 
 use super::*;
 use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::fs::{self, File};
+use std::io;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
 
 
-lazy_static! {
-    static ref PUSHES_URL: String = format!("{}pushes", BASE_URL);
-}
+const PUSHES_PATH: &str = "pushes";
+
+/// Largest `limit` accepted by the list API
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// `limit` of `ListCondition::default()`
+const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/// `AllPushes` pauses before the next page when fewer rate limit units remain
+const DEFAULT_PAUSE_THRESHOLD: i64 = 1000;
+
+/// Longest pause of `AllPushes` before the next page
+const DEFAULT_MAX_PAUSE: Duration = Duration::from_secs(5 * 60);
+
+/// Number of requests `get_pushes()` sends at a time
+const MAX_CONCURRENT_GETS: usize = 8;
+
+/// Number of redirects followed by `download_file()`
+const MAX_DOWNLOAD_REDIRECTS: u32 = 10;
+
+/// Number of retries done by `create_push_idempotent()`
+#[cfg(feature = "idempotent")]
+const IDEMPOTENT_RETRIES: u32 = 3;
+
+/// Longest wait for a rate limit reset between attempts of `create_push_idempotent()`
+#[cfg(feature = "idempotent")]
+const MAX_IDEMPOTENT_WAIT: Duration = Duration::from_secs(60);
 
 /// Push target type
 #[derive(Debug)]
@@ -20,14 +53,17 @@ pub enum Target {
     Device(String),
     /// Send the push to a email address
     Email(String),
-    /// Send the push to all subscribers to a channel
+    /// Send the push to all subscribers to a channel, given the channel's tag (not its iden)
+    ///
+    /// The API only accepts channel tags. The tag of a subscribed channel can be
+    /// found in [`subscription::Channel`](../subscription/struct.Channel.html).
     Channel(String),
     /// Send the push to all users who have granted access to this OAuth iden
     Client(String)
 }
 
 /// Request type of the push API
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Request<'a> {
     /// Parameters for note type push
     Note {
@@ -44,6 +80,150 @@ pub enum Request<'a> {
         body: &'a str,
         /// The url to open.
         url: &'a str
+    },
+    /// Parameters for file type push, of a file already uploaded
+    File {
+        /// The name of the file.
+        file_name: &'a str,
+        /// The MIME type of the file.
+        file_type: &'a str,
+        /// The url where the file can be downloaded.
+        file_url: &'a str,
+        /// A message to go with the file.
+        body: &'a str,
+    }
+}
+
+/// Owned version of [`Request`](enum.Request.html), e.g. for a push sent from another thread
+#[derive(Clone, Debug)]
+pub enum OwnedRequest {
+    /// See [`Request::Note`](enum.Request.html#variant.Note)
+    Note {
+        /// The note's title.
+        title: String,
+        /// The note's message.
+        body: String,
+    },
+    /// See [`Request::Link`](enum.Request.html#variant.Link)
+    Link {
+        /// The link's title.
+        title: String,
+        /// A message associated with the link.
+        body: String,
+        /// The url to open.
+        url: String,
+    },
+    /// See [`Request::File`](enum.Request.html#variant.File)
+    File {
+        /// The name of the file.
+        file_name: String,
+        /// The MIME type of the file.
+        file_type: String,
+        /// The url where the file can be downloaded.
+        file_url: String,
+        /// A message to go with the file.
+        body: String,
+    }
+}
+
+impl Target {
+    /// Target all subscribers of the channel with this tag.
+    ///
+    /// This is the tag chosen when the channel was created, not the channel's iden.
+    pub fn channel(tag: &str) -> Target {
+        Target::Channel(tag.to_owned())
+    }
+
+    /// Check the target before sending to it: identifiers must be non-empty and without whitespace.
+    fn validate(&self) -> ::std::result::Result<(), PushbulletError> {
+        let (name, value) = match *self {
+            Target::Broadcast => return Ok(()),
+            Target::Device(ref iden) => ("device iden", iden),
+            Target::Email(ref email) => ("email", email),
+            Target::Channel(ref tag) => ("channel tag", tag),
+            Target::Client(ref iden) => ("client iden", iden),
+        };
+        if value.is_empty() {
+            Err(PushbulletError::InvalidRequest(format!("empty {}", name)))
+        } else if value.chars().any(char::is_whitespace) {
+            Err(PushbulletError::InvalidRequest(format!("{} contains whitespace: {:?}", name, value)))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = PushbulletError;
+
+    /// Parse `broadcast`, `device:<iden>`, `email:<address>`, `channel:<tag>` or `client:<iden>`,
+    /// e.g. from a command line argument.
+    fn from_str(s: &str) -> ::std::result::Result<Target, PushbulletError> {
+        let target = match s.split_once(':') {
+            None if s == "broadcast" => Target::Broadcast,
+            Some(("device", iden)) => Target::Device(iden.to_owned()),
+            Some(("email", email)) => Target::Email(email.to_owned()),
+            Some(("channel", tag)) => Target::Channel(tag.to_owned()),
+            Some(("client", iden)) => Target::Client(iden.to_owned()),
+            _ => return Err(PushbulletError::InvalidRequest(format!(
+                "unknown target {:?}, expected broadcast, device:, email:, channel: or client:", s)))
+        };
+        target.validate()?;
+        Ok(target)
+    }
+}
+
+impl<'a> Request<'a> {
+    /// Note with a body only; the title is left out of the push.
+    pub fn note_body(body: &'a str) -> Request<'a> {
+        Request::Note { title: "", body }
+    }
+
+    /// Link push if `url` is given, otherwise a note push.
+    pub fn smart(title: &'a str, body: &'a str, url: Option<&'a str>) -> Request<'a> {
+        match url {
+            Some(url) => Request::Link { title, body, url },
+            None => Request::Note { title, body }
+        }
+    }
+
+    /// Check the request before sending it, e.g. that a link's url parses.
+    fn validate(&self) -> ::std::result::Result<(), PushbulletError> {
+        match *self {
+            Request::Note { .. } => Ok(()),
+            Request::Link { url, .. } | Request::File { file_url: url, .. } => match Url::parse(url) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(PushbulletError::InvalidRequest(format!("invalid url {:?}: {}", url, e)))
+            }
+        }
+    }
+}
+
+impl OwnedRequest {
+    /// Borrow as a `Request`.
+    pub fn as_request(&self) -> Request<'_> {
+        match *self {
+            OwnedRequest::Note { ref title, ref body } => Request::Note { title, body },
+            OwnedRequest::Link { ref title, ref body, ref url } => Request::Link { title, body, url },
+            OwnedRequest::File { ref file_name, ref file_type, ref file_url, ref body } =>
+                Request::File { file_name, file_type, file_url, body },
+        }
+    }
+}
+
+impl<'a> From<Request<'a>> for OwnedRequest {
+    fn from(request: Request<'a>) -> OwnedRequest {
+        match request {
+            Request::Note { title, body } => OwnedRequest::Note { title: title.to_owned(), body: body.to_owned() },
+            Request::Link { title, body, url } =>
+                OwnedRequest::Link { title: title.to_owned(), body: body.to_owned(), url: url.to_owned() },
+            Request::File { file_name, file_type, file_url, body } => OwnedRequest::File {
+                file_name: file_name.to_owned(),
+                file_type: file_type.to_owned(),
+                file_url: file_url.to_owned(),
+                body: body.to_owned(),
+            },
+        }
     }
 }
 
@@ -57,27 +237,37 @@ pub struct Response {
     pub body: String,
     /// Creation time in floating point seconds (unix timestamp)
     pub created: f64,
-    /// Direction the push was sent in, can be "self", "outgoing", or "incoming"
+    /// Direction the push was sent in, can be "self", "outgoing", or "incoming".
+    /// Empty for a deleted push.
+    #[serde(default)]
     pub direction: String,
     /// `true` if the push has been dismissed by any device or if any device was active when the push was received
+    #[serde(default)]
     pub dismissed: bool,
     /// Unique identifier for this object
     pub iden: String,
     /// Last modified time in floating point seconds (unix timestamp)
     pub modified: f64,
     /// Email address of the receiver
+    #[serde(default)]
     pub receiver_email: String,
     /// Canonical email address of the receiver
+    #[serde(default)]
     pub receiver_email_normalized: String,
     /// User iden of the receiver
+    #[serde(default)]
     pub receiver_iden: String,
     /// Email address of the sender
+    #[serde(default)]
     pub sender_email: String,
     /// Canonical email address of the sender
+    #[serde(default)]
     pub sender_email_normalized: String,
     /// User iden of the sender
+    #[serde(default)]
     pub sender_iden: String,
     /// Name of the sender
+    #[serde(default)]
     pub sender_name: String,
     /// Title of the push, used for all types of pushes
     #[serde(default)]
@@ -85,12 +275,139 @@ pub struct Response {
     /// URL field, used for `push_type="link"` pushes
     #[serde(default)]
     pub url: String,
-    /// Type of the push, one of "note", "file", "link".
-    #[serde(rename = "type")]
+    /// The name of the file, used for `push_type="file"` pushes
+    #[serde(default)]
+    pub file_name: String,
+    /// The MIME type of the file, used for `push_type="file"` pushes
+    #[serde(default)]
+    pub file_type: String,
+    /// The url where the file can be downloaded, used for `push_type="file"` pushes
+    #[serde(default)]
+    pub file_url: String,
+    /// URL of an image of the file, e.g. a thumbnail, used for `push_type="file"` pushes
+    #[serde(default)]
+    pub image_url: String,
+    /// Type of the push, one of "note", "file", "link". Empty for a deleted push.
+    #[serde(rename = "type", default)]
     pub push_type: String,
+    /// Iden of the channel the push was sent to
+    pub channel_iden: Option<String>,
+    /// Iden of the OAuth client the push was sent through
+    pub client_iden: Option<String>,
+    /// Unique identifier set by the client that created the push, used to drop duplicates
+    #[serde(default)]
+    pub guid: Option<String>,
+    /// Guids of the apps that were awake when the push was sent
+    #[serde(default)]
+    pub awake_app_guids: Option<Vec<String>>,
+    /// Width of the image in pixels, for `push_type="file"` pushes of an image
+    #[serde(default)]
+    pub image_width: Option<u32>,
+    /// Height of the image in pixels, for `push_type="file"` pushes of an image
+    #[serde(default)]
+    pub image_height: Option<u32>,
+}
+
+/// Direction a push was sent in, see [`Response::direction_kind()`](struct.Response.html#method.direction_kind)
+#[derive(Clone, Debug, PartialEq)]
+pub enum Direction {
+    /// "self": sent by the user to themselves
+    ToSelf,
+    /// "outgoing": sent by the user to someone else
+    Outgoing,
+    /// "incoming": sent to the user by someone else
+    Incoming,
+    /// A direction not known to this crate
+    Other(String),
+}
+
+impl<'a> From<&'a str> for Direction {
+    fn from(s: &'a str) -> Direction {
+        match s {
+            "self" => Direction::ToSelf,
+            "outgoing" => Direction::Outgoing,
+            "incoming" => Direction::Incoming,
+            other => Direction::Other(other.to_owned())
+        }
+    }
+}
+
+/// Sender or receiver of a push, borrowed from a [`Response`](struct.Response.html)
+#[derive(Debug, PartialEq)]
+pub struct Person<'a> {
+    /// User iden
+    pub iden: &'a str,
+    /// Email address
+    pub email: &'a str,
+    /// Canonical email address
+    pub email_normalized: &'a str,
+    /// Name, only known for the sender
+    pub name: Option<&'a str>,
 }
 
 impl Response {
+    /// One-line description for logging, e.g. `[note] "Space Travel Ideas" from Elon Musk`.
+    ///
+    /// Without a title, the first line of the body (or the url of a link) is used.
+    /// The subject and the sender are left out when empty.
+    pub fn summary(&self) -> String {
+        let mut summary = format!("[{}]", self.push_type);
+        let subject = [&self.title, &self.body, &self.url].iter()
+            .filter_map(|s| s.lines().next())
+            .find(|line| !line.is_empty());
+        if let Some(subject) = subject {
+            summary.push_str(&format!(" {:?}", subject));
+        }
+        let sender = if self.sender_name.is_empty() { &self.sender_email } else { &self.sender_name };
+        if !sender.is_empty() {
+            summary.push_str(" from ");
+            summary.push_str(sender);
+        }
+        summary
+    }
+
+    /// Request to send this push again, e.g. to another target.
+    ///
+    /// A file push refers to the already uploaded `file_url`. Fails for other push types.
+    pub fn to_request(&self) -> ::std::result::Result<Request<'_>, PushbulletError> {
+        match self.push_type.as_str() {
+            "note" => Ok(Request::Note { title: &self.title, body: &self.body }),
+            "link" => Ok(Request::Link { title: &self.title, body: &self.body, url: &self.url }),
+            "file" => Ok(Request::File {
+                file_name: &self.file_name,
+                file_type: &self.file_type,
+                file_url: &self.file_url,
+                body: &self.body,
+            }),
+            other => Err(PushbulletError::InvalidRequest(format!("cannot resend a push of type {:?}", other)))
+        }
+    }
+
+    /// Get `direction` field as a `Direction`.
+    pub fn direction_kind(&self) -> Direction {
+        Direction::from(self.direction.as_str())
+    }
+
+    /// Sender fields grouped together.
+    pub fn sender(&self) -> Person<'_> {
+        Person {
+            iden: &self.sender_iden,
+            email: &self.sender_email,
+            email_normalized: &self.sender_email_normalized,
+            name: Some(&self.sender_name),
+        }
+    }
+
+    /// Receiver fields grouped together.
+    pub fn receiver(&self) -> Person<'_> {
+        Person {
+            iden: &self.receiver_iden,
+            email: &self.receiver_email,
+            email_normalized: &self.receiver_email_normalized,
+            name: None,
+        }
+    }
+
     /// Get `created` field as DateTime.
     pub fn created_time(&self) -> DateTime<Utc> {
         float_unix_time2date_time(self.created)
@@ -100,22 +417,141 @@ impl Response {
     pub fn modified_time(&self) -> DateTime<Utc> {
         float_unix_time2date_time(self.modified)
     }
+
+    /// Time since the push was created, e.g. for a "5 minutes ago" label.
+    ///
+    /// Negative if the clock of this machine is behind the server's.
+    pub fn age(&self) -> chrono::Duration {
+        Utc::now() - self.created_time()
+    }
 }
 
 #[derive(Deserialize, Debug)]
 struct ResponseVec {
-    pub pushes: Vec<Response>
+    pub pushes: Vec<Response>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// Reads a list response, calling `f` for each push as soon as it is parsed
+/// instead of collecting the pushes into a `Vec`. The value is the cursor.
+struct PushesVisitor<'a, F> {
+    f: &'a mut F,
+}
+
+impl<'de, 'a, F: FnMut(Response)> Visitor<'de> for PushesVisitor<'a, F> {
+    type Value = Option<String>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of pushes")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> ::std::result::Result<Option<String>, A::Error> {
+        let mut cursor = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "pushes" => {
+                    map.next_value_seed(PushesVisitor { f: &mut *self.f })?;
+                }
+                "cursor" => cursor = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(cursor)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> ::std::result::Result<Option<String>, A::Error> {
+        while let Some(push) = seq.next_element::<Response>()? {
+            (self.f)(push);
+        }
+        Ok(None)
+    }
+}
+
+impl<'de, 'a, F: FnMut(Response)> DeserializeSeed<'de> for PushesVisitor<'a, F> {
+    type Value = Option<String>;
+
+    /// Deserialize the `pushes` array.
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> ::std::result::Result<Option<String>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+/// Call `f` for each push of a list response read from `reader`, returning the cursor.
+fn for_each_push<R: io::Read, F: FnMut(Response)>(reader: R, f: &mut F)
+                                                  -> ::std::result::Result<Option<String>, serde_json::Error> {
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let cursor = de::Deserializer::deserialize_map(&mut deserializer, PushesVisitor { f })?;
+    deserializer.end()?;
+    Ok(cursor)
+}
+
+/// One page of the push history, see
+/// [`PushbulletClient::list_push_paged()`](../struct.PushbulletClient.html#method.list_push_paged)
+#[derive(Debug)]
+pub struct PushPage {
+    /// Pushes of this page
+    pub pushes: Vec<Response>,
+    /// Cursor of the next page, to set as `ListCondition::cursor`
+    pub cursor: Option<String>,
+    /// `true` if the API returned a cursor, i.e. there are more pushes to fetch
+    pub has_more: bool,
+}
+
+impl From<ResponseVec> for PushPage {
+    fn from(r: ResponseVec) -> PushPage {
+        PushPage {
+            has_more: r.cursor.is_some(),
+            pushes: r.pushes,
+            cursor: r.cursor,
+        }
+    }
+}
+
+/// Only the iden of a push response, see `create_push_iden()`
+#[derive(Deserialize, Debug)]
+struct PushIden {
+    pub iden: String,
 }
 
 pub type Result = ::std::result::Result<(Response, ResponseHeaders), Box<dyn Error>>;
 
+/// Push paired with its creation time
+pub type TimedPush = (DateTime<Utc>, Response);
+
+/// Error of [`PushbulletClient::delete_pushes_before()`](../struct.PushbulletClient.html#method.delete_pushes_before),
+/// with the number of pushes deleted before the failure
+#[derive(Debug)]
+pub struct DeletePushesError {
+    /// Number of pushes deleted before the failure
+    pub deleted: usize,
+    /// Error of the failed request
+    pub error: Box<dyn Error>,
+}
+
+impl fmt::Display for DeletePushesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Failed after deleting {} pushes: {}", self.deleted, self.error)
+    }
+}
+
+impl Error for DeletePushesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
 
 /// Parameters for [`PushbulletClient::list_push()`](../struct.PushbulletClient.html#method.list_push)
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Clone, Debug)]
 pub struct ListCondition {
     /// Don't return deleted pushes
     pub active: bool,
     /// Limit on the number of results returned
+    ///
+    /// `0` leaves the parameter out, so the API default page size is used.
     pub limit: u32,
     /// Request pushes modified after this timestamp.
     ///
@@ -129,103 +565,1759 @@ impl ListCondition {
     pub fn new(limit: u32) -> ListCondition {
         ListCondition {
             active: true,
-            limit: limit,
+            limit,
             modified_after: None,
             cursor: None,
         }
     }
 
+    /// Condition that also returns deleted pushes, e.g. for auditing. It is sent as
+    /// `active=false`.
+    ///
+    /// Deleted pushes have `active == false` and only their iden and timestamps set.
+    pub fn including_deleted(limit: u32) -> ListCondition {
+        ListCondition {
+            active: false,
+            ..ListCondition::new(limit)
+        }
+    }
+
     /// Set `modified_after` field with DateTime.
     pub fn set_modified_after(&mut self, t: DateTime<Utc>) {
         self.modified_after = Some(date_time2float_unix_time(t));
     }
+
+    /// Set `modified_after` field with a raw timestamp, e.g. the `modified` field of a push.
+    ///
+    /// Unlike [`set_modified_after()`](#method.set_modified_after), the value is kept
+    /// exactly as given, without a round trip through `DateTime`.
+    pub fn set_modified_after_raw(&mut self, t: f64) {
+        self.modified_after = Some(t);
+    }
+
+    fn query_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("active", format!("{}", self.active))];
+        if self.limit > 0 {
+            params.push(("limit", format!("{}", self.limit)));
+        }
+        if let Some(t) = self.modified_after {
+            params.push(("modified_after", format_timestamp_param(t)));
+        }
+        if let Some(ref s) = self.cursor {
+            params.push(("cursor", s.to_string()));
+        }
+        params
+    }
+
+    /// Save the position of the pagination as an opaque string, e.g. to resume
+    /// after a restart with [`from_resume_token()`](#method.from_resume_token).
+    ///
+    /// The token holds `active`, `limit`, `modified_after` and `cursor`.
+    pub fn to_resume_token(&self) -> String {
+        let token = ResumeToken {
+            active: self.active,
+            limit: self.limit,
+            modified_after: self.modified_after.map(format_timestamp_param),
+            cursor: self.cursor.clone(),
+        };
+        base64::encode_config(serde_json::to_vec(&token).unwrap(), base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Restore a condition saved with [`to_resume_token()`](#method.to_resume_token).
+    pub fn from_resume_token(token: &str) -> ::std::result::Result<ListCondition, Box<dyn Error>> {
+        let json = base64::decode_config(token, base64::URL_SAFE_NO_PAD)?;
+        let token: ResumeToken = serde_json::from_slice(&json)?;
+        let modified_after = match token.modified_after {
+            Some(t) => Some(t.parse()?),
+            None => None
+        };
+        Ok(ListCondition {
+            active: token.active,
+            limit: token.limit,
+            modified_after,
+            cursor: token.cursor,
+        })
+    }
 }
 
-impl PushbulletClient {
-    /// Send a push to a device or another person.
-    pub fn create_push(&self, target: &Target, request: Request) -> Result {
-        debug!("target: {:?}, request: {:?}", target, request);
-        let mut json = match request {
-            Request::Note { title, body } => json!({
-                "type": "note",
-                "title": title,
-                "body": body
-            }),
-            Request::Link { title, body, url } => json!({
-                "type": "link",
-                "title": title,
-                "body": body,
-                "url": url
-            })
+impl Default for ListCondition {
+    /// Active pushes, 20 per page.
+    ///
+    /// ```
+    /// # use pshbullet_client::push::ListCondition;
+    /// let condition = ListCondition { limit: 50, ..Default::default() };
+    /// assert!(condition.active);
+    /// assert_eq!(condition.cursor, None);
+    /// ```
+    fn default() -> ListCondition {
+        ListCondition::new(DEFAULT_PAGE_SIZE)
+    }
+}
+
+/// Contents of a resume token; `modified_after` is kept as a string to round-trip exactly.
+#[derive(Serialize, Deserialize, Debug)]
+struct ResumeToken {
+    active: bool,
+    limit: u32,
+    modified_after: Option<String>,
+    cursor: Option<String>,
+}
+
+/// Keep only pushes whose `direction` is "incoming".
+///
+/// The list API has no direction filter, so this is applied to the returned pushes,
+/// e.g. those listed with `ListCondition::new()`, which only lists active pushes.
+pub fn only_incoming(pushes: Vec<Response>) -> Vec<Response> {
+    pushes.into_iter().filter(|p| p.direction == "incoming").collect()
+}
+
+/// Group pushes by `sender_iden`, keeping their order within each group.
+///
+/// Pushes with an empty `sender_iden` are grouped under `None`.
+pub fn group_by_sender(pushes: Vec<Response>) -> HashMap<Option<String>, Vec<Response>> {
+    let mut groups: HashMap<Option<String>, Vec<Response>> = HashMap::new();
+    for push in pushes {
+        let sender = if push.sender_iden.is_empty() {
+            None
+        } else {
+            Some(push.sender_iden.clone())
         };
-        match *target {
-            Target::Broadcast => (),
-            Target::Device(ref id) => json["device_iden"] = json!(id),
-            Target::Email(ref id) => json["email"] = json!(id),
-            Target::Channel(ref id) => json["channel_tag"] = json!(id),
-            Target::Client(ref id) => json["client_iden"] = json!(id)
+        groups.entry(sender).or_default().push(push);
+    }
+    groups
+}
+
+/// Build the JSON body that [`PushbulletClient::create_push()`](../struct.PushbulletClient.html#method.create_push)
+/// sends, e.g. to check it in tests without a server.
+///
+/// The target and request are not validated, unlike in `create_push()`.
+///
+/// ```
+/// # #[macro_use] extern crate serde_json;
+/// # use pshbullet_client::push::*;
+/// # fn main() {
+/// let json = build_push_json(&Target::Device(String::from("ujpah72o0sjAoRtnM0jc")), &Request::note_body("hi"));
+/// assert_eq!(json, json!({"type": "note", "body": "hi", "device_iden": "ujpah72o0sjAoRtnM0jc"}));
+/// # }
+/// ```
+pub fn build_push_json(target: &Target, request: &Request) -> Value {
+    let mut json = match *request {
+        Request::Note { title: "", body } => json!({
+            "type": "note",
+            "body": body
+        }),
+        Request::Note { title, body } => json!({
+            "type": "note",
+            "title": title,
+            "body": body
+        }),
+        Request::Link { title, body, url } => json!({
+            "type": "link",
+            "title": title,
+            "body": body,
+            "url": url
+        }),
+        Request::File { file_name, file_type, file_url, body } => json!({
+            "type": "file",
+            "file_name": file_name,
+            "file_type": file_type,
+            "file_url": file_url,
+            "body": body
+        })
+    };
+    apply_target(&mut json, target);
+    json
+}
+
+/// Validate a push before sending it and build its JSON.
+fn prepare_push(target: &Target, request: &Request) -> ::std::result::Result<Value, PushbulletError> {
+    debug!("target: {:?}, request: {:?}", target, request);
+    target.validate()?;
+    request.validate()?;
+    let json = build_push_json(target, request);
+    debug!("json: {}", json);
+    Ok(json)
+}
+
+/// JSON keys of the push targets
+const TARGET_KEYS: [&str; 4] = ["device_iden", "email", "channel_tag", "client_iden"];
+
+/// Set the target field of a push JSON, removing any other target field.
+fn apply_target(json: &mut Value, target: &Target) {
+    if let Value::Object(ref mut fields) = *json {
+        for key in TARGET_KEYS.iter() {
+            fields.remove(*key);
         }
-        debug!("json: {}", json);
+    }
+    match *target {
+        Target::Broadcast => (),
+        Target::Device(ref id) => json["device_iden"] = json!(id),
+        Target::Email(ref id) => json["email"] = json!(id),
+        Target::Channel(ref id) => json["channel_tag"] = json!(id),
+        Target::Client(ref id) => json["client_iden"] = json!(id)
+    }
+}
 
-        match self.post(&*PUSHES_URL, json) {
-            Ok((raw_response, headers)) => {
-                let r: Response = serde_json::from_reader(raw_response)?;
-                Ok((r, headers))
-            }
-            Err(e) => Err(e)
+/// Add the `extra` fields to a push JSON, replacing generated fields with the same key.
+fn merge_extra(mut json: Value, extra: serde_json::Map<String, Value>) -> Value {
+    if let Value::Object(ref mut fields) = json {
+        fields.extend(extra);
+    }
+    json
+}
+
+/// Pair pushes with their creation time, oldest first.
+fn timeline(pushes: Vec<Response>) -> Vec<TimedPush> {
+    let mut timeline: Vec<_> = pushes.into_iter().map(|p| (p.created_time(), p)).collect();
+    timeline.sort_by_key(|&(created, _)| created);
+    timeline
+}
+
+/// Whether `url` is served by Pushbullet over HTTPS and may be sent the access token
+fn may_send_token(url: &Url) -> bool {
+    url.scheme() == "https" && url.host_str().is_some_and(|host| {
+        ["pushbullet.com", "pushbulletusercontent.com"].iter()
+            .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+    })
+}
+
+/// Whether a failed push is worth sending again: rate limiting, a timeout or a
+/// connection failure.
+#[cfg(feature = "idempotent")]
+fn is_retryable(e: &(dyn Error + 'static)) -> bool {
+    match e.downcast_ref::<PushbulletError>() {
+        Some(PushbulletError::Http { status, .. }) => *status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+        Some(_) => false,
+        None => e.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout() || e.is_connect())
+    }
+}
+
+/// Wait before the `retry`-th attempt of `create_push_idempotent()`: until the rate limit
+/// resets if the error reports it, at most `MAX_IDEMPOTENT_WAIT`, and otherwise 2, 4, 8s.
+#[cfg(feature = "idempotent")]
+fn idempotent_retry_delay(e: &(dyn Error + 'static), retry: u32, now: DateTime<Utc>) -> Duration {
+    e.downcast_ref::<PushbulletError>()
+        .and_then(PushbulletError::retry_after)
+        .and_then(|reset| reset.signed_duration_since(now).to_std().ok())
+        .map_or(Duration::from_secs(1 << retry), |wait| wait.min(MAX_IDEMPOTENT_WAIT))
+}
+
+/// Iterator over the push history, following the cursor across pages.
+///
+/// When fewer than 1000 rate limit units remain after a page, the next page is only
+/// requested once the rate limit resets, see [`rate_limit_pause()`](#method.rate_limit_pause).
+///
+/// See [`PushbulletClient::list_all_pushes()`](../struct.PushbulletClient.html#method.list_all_pushes)
+pub struct AllPushes<'a> {
+    client: &'a PushbulletClient,
+    condition: ListCondition,
+    pushes: ::std::vec::IntoIter<Response>,
+    done: bool,
+    seen: Option<HashSet<String>>,
+    pause_threshold: i64,
+    max_pause: Duration,
+    headers: Option<ResponseHeaders>,
+}
+
+impl<'a> AllPushes<'a> {
+    /// Skip pushes already returned, e.g. a push that moved to the next page
+    /// because it was modified during the pagination.
+    ///
+    /// The iden of every returned push is kept until the iterator is dropped,
+    /// roughly 50 bytes per push.
+    pub fn dedup(mut self) -> AllPushes<'a> {
+        self.seen = Some(HashSet::new());
+        self
+    }
+
+    /// Before requesting the next page, sleep until the rate limit resets if fewer than
+    /// `threshold` units remain, but no longer than `max_pause`.
+    ///
+    /// Defaults to 1000 units and 5 minutes. A `threshold` of `0` never pauses.
+    pub fn rate_limit_pause(mut self, threshold: i64, max_pause: Duration) -> AllPushes<'a> {
+        self.pause_threshold = threshold;
+        self.max_pause = max_pause;
+        self
+    }
+
+    fn pause(&mut self) {
+        let wait = self.headers.take()
+            .and_then(|headers| headers.reset_wait_below(self.pause_threshold, Utc::now()));
+        if let Some(wait) = wait {
+            let wait = wait.min(self.max_pause);
+            info!("rate limit almost exhausted, waiting {:?} before the next page", wait);
+            thread::sleep(wait);
         }
     }
+}
 
-    /// Request push history.
-    pub fn list_push(&self, condition: &ListCondition)
-                     -> ::std::result::Result<(Vec<Response>, ResponseHeaders), Box<dyn Error>> {
-        debug!("condition: {:?}", condition);
-        let mut params = vec![
-            ("active", format!("{}", condition.active)),
-            ("limit", format!("{}", condition.limit)),
-        ];
-        condition.modified_after.map(|t| {
-            params.push(("modified_after", format!("{:e}", t)))
-        });
-        condition.cursor.as_ref().map(|s| {
-            params.push(("cursor", s.to_string()))
-        });
-        let url = Url::parse_with_params(&*PUSHES_URL, &params).unwrap().into_string();
-        match self.get(&url) {
-            Ok((raw_response, headers)) => {
-                let r: ResponseVec = serde_json::from_reader(raw_response)?;
-                Ok((r.pushes, headers))
+impl<'a> Iterator for AllPushes<'a> {
+    type Item = ::std::result::Result<Response, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(push) = self.pushes.next() {
+                if let Some(ref mut seen) = self.seen {
+                    if !seen.insert(push.iden.clone()) {
+                        debug!("skipping duplicate push: {}", push.iden);
+                        continue;
+                    }
+                }
+                return Some(Ok(push));
+            }
+            if self.done {
+                return None;
+            }
+            self.pause();
+            match self.client.list_push_page(&self.condition) {
+                Ok((r, headers)) => {
+                    self.headers = Some(headers);
+                    self.done = r.cursor.is_none();
+                    self.condition.cursor = r.cursor;
+                    self.pushes = r.pushes.into_iter();
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
             }
-            Err(e) => Err(e)
         }
     }
 }
 
+/// Push waiting to be sent, see
+/// [`PushbulletClient::schedule_push()`](../struct.PushbulletClient.html#method.schedule_push)
+#[derive(Debug)]
+pub struct ScheduledPush {
+    state: Arc<(Mutex<ScheduleState>, Condvar)>,
+    thread: thread::JoinHandle<Option<SendResult>>,
+}
 
-#[cfg(test)]
-mod tests {
-    use serde_json;
-    use super::*;
+/// `Result` that can be returned from another thread
+type SendResult = ::std::result::Result<(Response, ResponseHeaders), Box<dyn Error + Send + Sync>>;
 
-    #[test]
-    fn deserialize_test() {
-        let deserialized: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
-        assert_eq!(deserialized.pushes.len(), 1);
-        let r = &deserialized.pushes[0];
-        assert_eq!(r.push_type, "note");
-        assert_eq!(r.active, true);
-        assert_eq!(r.direction, "self");
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScheduleState {
+    Pending,
+    Cancelled,
+    Sent,
+}
 
-        assert_eq!(r.modified, 1.412047948579031e+09);
-        //        println!("{}", r.modified_time().to_rfc3339());
-        let diff = r.modified_time().signed_duration_since(Utc.ymd(2014, 9, 30).and_hms(3, 32, 28));
-        assert!((diff.num_milliseconds() - 579).abs() < 10);
+impl ScheduledPush {
+    /// Cancel the push. Returns `false` if it was already sent or is being sent.
+    pub fn cancel(&self) -> bool {
+        let (ref lock, ref cvar) = *self.state;
+        let mut state = lock.lock().unwrap();
+        if *state != ScheduleState::Pending {
+            return false;
+        }
+        *state = ScheduleState::Cancelled;
+        cvar.notify_one();
+        true
+    }
 
-        assert_eq!(r.created, 1.412047948579029e+09);
-        //        println!("{}", r.created_time().to_rfc3339());
-        let diff = r.created_time().signed_duration_since(Utc.ymd(2014, 9, 30).and_hms(3, 32, 28));
-        assert!((diff.num_milliseconds() - 579).abs() < 10);
+    /// Wait until the push is sent, returning `None` if it was cancelled.
+    pub fn join(self) -> Option<Result> {
+        self.thread.join()
+            .unwrap_or_else(|panic| ::std::panic::resume_unwind(panic))
+            .map(|r| r.map_err(|e| e as Box<dyn Error>))
     }
+}
+
+impl PushbulletClient {
+    /// Send a push to a device or another person.
+    pub fn create_push(&self, target: &Target, request: Request) -> Result {
+        let json = prepare_push(target, &request)?;
+        self.post_push(json)
+    }
+
+    /// Send a push, returning only its iden, e.g. to delete it later.
+    ///
+    /// The other fields of the response are skipped instead of being deserialized.
+    pub fn create_push_iden(&self, target: &Target, request: Request) -> ::std::result::Result<String, Box<dyn Error>> {
+        let json = prepare_push(target, &request)?;
+        let (raw_response, _) = self.post(&self.api_url(PUSHES_PATH), json)?;
+        let r: PushIden = serde_json::from_reader(raw_response)?;
+        Ok(r.iden)
+    }
+
+    /// Send a push with additional JSON fields not modeled by `Request`,
+    /// e.g. `source_device_iden` or `guid`.
+    ///
+    /// A field of `extra` overrides the generated field of the same name, including
+    /// `type`. Target fields of `extra` are dropped, so that only `target` is sent.
+    pub fn create_push_with_extra(&self, target: &Target, request: Request,
+                                  extra: serde_json::Map<String, Value>) -> Result {
+        let mut json = merge_extra(prepare_push(target, &request)?, extra);
+        apply_target(&mut json, target);
+        debug!("json with extra: {}", json);
+        self.post_push(json)
+    }
+
+    /// Send `push` again to `target`, e.g. to forward a received push.
+    ///
+    /// See [`Response::to_request()`](push/struct.Response.html#method.to_request).
+    pub fn resend_push(&self, push: &Response, target: &Target) -> Result {
+        self.create_push(target, push.to_request()?)
+    }
+
+    /// Send a push after `delay` from a background thread, e.g. "in 10 minutes".
+    ///
+    /// Pushbullet has no scheduled delivery, so the push is only kept in this process:
+    /// it is lost if the process exits before it is sent. Dropping the returned
+    /// handle does not cancel the push.
+    ///
+    /// The target and request are checked right away, failing with
+    /// `PushbulletError::InvalidRequest` before any thread is started.
+    pub fn schedule_push(&self, delay: Duration, target: Target, request: OwnedRequest)
+            -> ::std::result::Result<ScheduledPush, PushbulletError> {
+        target.validate()?;
+        request.as_request().validate()?;
+        let state = Arc::new((Mutex::new(ScheduleState::Pending), Condvar::new()));
+        let thread_state = Arc::clone(&state);
+        let client = self.clone();
+        let thread = thread::spawn(move || {
+            let (ref lock, ref cvar) = *thread_state;
+            let (mut state, _) = cvar
+                .wait_timeout_while(lock.lock().unwrap(), delay, |state| *state == ScheduleState::Pending)
+                .unwrap();
+            if *state == ScheduleState::Cancelled {
+                debug!("scheduled push cancelled");
+                return None;
+            }
+            *state = ScheduleState::Sent;
+            drop(state);
+            Some(client.create_push(&target, request.as_request()).map_err(into_send_error))
+        });
+        Ok(ScheduledPush { state, thread })
+    }
+
+    /// Send a push with an `Idempotency-Key` header, for a proxy or gateway in front of
+    /// the API that drops requests repeating a key.
+    ///
+    /// The Pushbullet API itself ignores the header; it drops duplicates by the `guid`
+    /// field instead.
+    pub fn create_push_with_idempotency_key(&self, target: &Target, request: Request, key: &str) -> Result {
+        let json = prepare_push(target, &request)?;
+        debug!("idempotency key: {}", key);
+        let url = self.api_url(PUSHES_PATH);
+        debug!("url: {}", url);
+        self.send_push(self.http_client().post(&url).json(&json).header("Idempotency-Key", key))
+    }
+
+    /// Send a push with a timeout for this request only.
+    ///
+    /// `timeout` replaces the client-wide timeout (30 seconds by default in reqwest)
+    /// for sending the request and waiting for the response headers, whether it is
+    /// longer or shorter. Reading the response body is still bounded by the client-wide
+    /// timeout, which the blocking reqwest client applies to each read of the body.
+    pub fn create_push_with_timeout(&self, target: &Target, request: Request, timeout: Duration) -> Result {
+        let json = prepare_push(target, &request)?;
+        debug!("timeout: {:?}", timeout);
+        let url = self.api_url(PUSHES_PATH);
+        debug!("url: {}", url);
+        self.send_push(self.http_client().post(&url).json(&json).timeout(timeout))
+    }
+
+    /// Send a push with a generated `guid`, retrying on rate limiting, timeouts or
+    /// connection failures, up to 3 times.
+    ///
+    /// After a 429 the next attempt waits until the rate limit resets, for at most a
+    /// minute; otherwise it waits 2, 4, then 8 seconds.
+    ///
+    /// Every attempt reuses the same `guid`, so the server drops duplicates of a
+    /// push whose earlier attempt actually went through.
+    ///
+    /// Requires the `idempotent` feature.
+    #[cfg(feature = "idempotent")]
+    pub fn create_push_idempotent(&self, target: &Target, request: Request) -> Result {
+        let mut json = prepare_push(target, &request)?;
+        json["guid"] = json!(uuid::Uuid::new_v4().to_string());
+        debug!("guid: {}", json["guid"]);
+
+        let mut retries = 0;
+        loop {
+            match self.post_push(json.clone()) {
+                Err(ref e) if retries < IDEMPOTENT_RETRIES && is_retryable(e.as_ref()) => {
+                    retries += 1;
+                    let delay = idempotent_retry_delay(e.as_ref(), retries, Utc::now());
+                    warn!("retrying push in {:?} ({}/{}): {}", delay, retries, IDEMPOTENT_RETRIES, e);
+                    thread::sleep(delay);
+                }
+                result => return result
+            }
+        }
+    }
+
+    /// Get a single push by iden.
+    ///
+    /// The response is the bare push object, not wrapped in a `pushes` list.
+    pub fn get_push(&self, iden: &str) -> Result {
+        let url = format!("{}/{}", self.api_url(PUSHES_PATH), iden);
+        match self.get(&url) {
+            Ok((raw_response, headers)) => {
+                let r: Response = serde_json::from_reader(raw_response)?;
+                Ok((r, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Get several pushes by iden, one request each, sent concurrently.
+    ///
+    /// At most 8 requests are in flight at a time: the idens are fetched in batches
+    /// of 8, one thread per request, each batch starting once the previous one is done.
+    ///
+    /// The results are in the order of `idens`, so a missing push fails on its own.
+    pub fn get_pushes(&self, idens: &[&str]) -> Vec<Result> {
+        let mut results = Vec::with_capacity(idens.len());
+        for batch in idens.chunks(MAX_CONCURRENT_GETS) {
+            thread::scope(|scope| {
+                let handles: Vec<_> = batch.iter()
+                    .map(|iden| scope.spawn(move || self.get_push(iden).map_err(into_send_error)))
+                    .collect();
+                for h in handles {
+                    let r = h.join().unwrap_or_else(|panic| ::std::panic::resume_unwind(panic));
+                    results.push(r.map_err(|e| e as Box<dyn Error>));
+                }
+            });
+        }
+        results
+    }
+
+    /// Delete a push.
+    pub fn delete_push(&self, iden: &str) -> ::std::result::Result<ResponseHeaders, Box<dyn Error>> {
+        let url = format!("{}/{}", self.api_url(PUSHES_PATH), iden);
+        self.delete(&url).map(|(_, headers)| headers)
+    }
+
+    /// Delete pushes last modified before `cutoff`, returning how many were deleted.
+    ///
+    /// Only pushes with `direction == "self"` are deleted, so pushes received from
+    /// other people are left alone; see
+    /// [`delete_pushes_before_with_directions()`](#method.delete_pushes_before_with_directions)
+    /// to delete others. The whole history is paged through first.
+    ///
+    /// If a request fails, the error holds the number of pushes deleted until then.
+    pub fn delete_pushes_before(&self, cutoff: DateTime<Utc>) -> ::std::result::Result<usize, DeletePushesError> {
+        self.delete_pushes_before_with_directions(cutoff, &["self"])
+    }
+
+    /// Delete pushes last modified before `cutoff` whose `direction` is one of `directions`
+    /// ("self", "outgoing", "incoming"), returning how many were deleted.
+    pub fn delete_pushes_before_with_directions(&self, cutoff: DateTime<Utc>, directions: &[&str])
+            -> ::std::result::Result<usize, DeletePushesError> {
+        let mut idens = vec![];
+        for push in self.list_all_pushes(&ListCondition::new(MAX_PAGE_SIZE)) {
+            let push = push.map_err(|error| DeletePushesError { deleted: 0, error })?;
+            if directions.contains(&push.direction.as_str()) && push.modified_time() < cutoff {
+                idens.push(push.iden);
+            }
+        }
+        for (deleted, iden) in idens.iter().enumerate() {
+            self.delete_push(iden).map_err(|error| DeletePushesError { deleted, error })?;
+        }
+        Ok(idens.len())
+    }
+
+    /// Check that the file at `path` can be uploaded, i.e. that it is no larger than the
+    /// `max_upload_size` of the user's account, returning its size in bytes.
+    ///
+    /// Fails with `PushbulletError::FileTooLarge` otherwise, before any upload is attempted.
+    /// Passes if the account reports no `max_upload_size`.
+    pub fn check_upload_size(&self, path: &Path) -> ::std::result::Result<u64, Box<dyn Error>> {
+        let size = path.metadata()?.len();
+        let (user, _) = self.get_user_info()?;
+        Self::check_file_size(size, user.max_upload_size)?;
+        Ok(size)
+    }
+
+    /// Fail if a file of `size` bytes is larger than `max_upload_size`.
+    fn check_file_size(size: u64, max_upload_size: Option<f64>) -> ::std::result::Result<(), PushbulletError> {
+        match max_upload_size {
+            Some(max) if size as f64 > max => Err(PushbulletError::FileTooLarge { size, max_upload_size: max as u64 }),
+            _ => Ok(())
+        }
+    }
+
+    /// Download the file of a file push to `dest`, returning the number of bytes written.
+    ///
+    /// The access token is only sent to Pushbullet hosts over HTTPS, so it is not leaked
+    /// to a `file_url` pointing somewhere else or sent in the clear. Redirects are followed
+    /// one at a time, up to 10 of them, checking the scheme and host again for each.
+    ///
+    /// The file is written next to `dest` with a `.part` suffix and renamed to `dest`
+    /// once complete, so `dest` is left untouched if the download fails.
+    pub fn download_file(&self, file_url: &str, dest: &Path) -> ::std::result::Result<u64, Box<dyn Error>> {
+        let mut downloader = self.clone();
+        downloader.http_client = Arc::new(OnceLock::from(self.http_client_builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?));
+
+        let mut url = Url::parse(file_url)?;
+        let mut redirects = 0;
+        let raw_response = loop {
+            debug!("url: {}", url);
+            let mut request = downloader.http_client().get(url.clone());
+            if may_send_token(&url) {
+                request = self.authorize(request);
+            }
+            let raw_response = downloader.execute_with_retry(request)?;
+            if !raw_response.status().is_redirection() || redirects == MAX_DOWNLOAD_REDIRECTS {
+                break raw_response;
+            }
+            let location = raw_response.headers().get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok());
+            match location {
+                Some(location) => url = url.join(location)?,
+                None => break raw_response
+            }
+            redirects += 1;
+        };
+        let (mut raw_response, _) = self.check_status(raw_response)?;
+
+        let mut part = dest.as_os_str().to_owned();
+        part.push(".part");
+        let part = PathBuf::from(part);
+        let written = File::create(&part).and_then(|mut file| io::copy(&mut raw_response, &mut file));
+        match written {
+            Ok(written) => {
+                fs::rename(&part, dest)?;
+                Ok(written)
+            }
+            Err(e) => {
+                if let Err(e) = fs::remove_file(&part) {
+                    error!("failed to remove {}: {}", part.display(), e);
+                }
+                Err(From::from(e))
+            }
+        }
+    }
+
+    fn post_push(&self, json: Value) -> Result {
+        let url = self.api_url(PUSHES_PATH);
+        debug!("url: {}", url);
+        self.send_push(self.http_client().post(&url).json(&json))
+    }
+
+    fn send_push(&self, request: reqwest::blocking::RequestBuilder) -> Result {
+        match self.send(request) {
+            Ok((raw_response, headers)) => {
+                let r: Response = serde_json::from_reader(raw_response)?;
+                Ok((r, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Send a push from caller-supplied JSON, for fields or push types not modeled by `Request`.
+    ///
+    /// The JSON is posted as is: the caller is responsible for the `type` field and
+    /// for any target field (`device_iden`, `email`, `channel_tag` or `client_iden`).
+    pub fn create_push_raw(&self, json: Value) -> Result {
+        debug!("json: {}", json);
+        self.post_push(json)
+    }
+
+    /// Send the same push to each of `targets`.
+    ///
+    /// Results are returned in the order of `targets`, so partial failures can be inspected.
+    pub fn create_push_multi(&self, targets: &[Target], request: &Request) -> Vec<Result> {
+        targets.iter()
+            .map(|target| self.create_push(target, request.clone()))
+            .collect()
+    }
+
+    /// Send the same push to each of `targets` in order, stopping at the first failure.
+    ///
+    /// On failure, the pushes to the targets before the failing one have already been
+    /// sent, and the targets after it were not attempted.
+    pub fn create_push_multi_fail_fast(&self, targets: &[Target], request: &Request)
+                                       -> ::std::result::Result<Vec<(Response, ResponseHeaders)>, Box<dyn Error>> {
+        targets.iter()
+            .map(|target| self.create_push(target, request.clone()))
+            .collect()
+    }
+
+    /// Send the same push to each of `emails`, pairing each address with its result.
+    ///
+    /// A failure for one address does not stop the push to the others.
+    pub fn push_to_emails(&self, emails: &[&str], request: &Request) -> Vec<(String, Result)> {
+        emails.iter()
+            .map(|email| (email.to_string(), self.create_push(&Target::Email(email.to_string()), request.clone())))
+            .collect()
+    }
+
+    /// Send a push to each pushable device of the user except `exclude_device_iden`,
+    /// e.g. the device the push originates from.
+    ///
+    /// Results are returned per device. If the devices cannot be listed, the only
+    /// result is that error.
+    pub fn broadcast_except(&self, exclude_device_iden: &str, request: &Request) -> Vec<Result> {
+        match self.list_pushable_devices() {
+            Ok((devices, _)) => {
+                let targets: Vec<Target> = devices.iter()
+                    .filter(|d| d.iden != exclude_device_iden)
+                    .map(Target::from)
+                    .collect();
+                self.create_push_multi(&targets, request)
+            }
+            Err(e) => vec![Err(e)]
+        }
+    }
+
+    /// Request push history.
+    pub fn list_push(&self, condition: &ListCondition)
+                     -> ::std::result::Result<(Vec<Response>, ResponseHeaders), Box<dyn Error>> {
+        self.list_push_page(condition).map(|(r, headers)| (r.pushes, headers))
+    }
+
+    /// Request one page of push history, along with whether more pages exist.
+    pub fn list_push_paged(&self, condition: &ListCondition)
+                           -> ::std::result::Result<(PushPage, ResponseHeaders), Box<dyn Error>> {
+        self.list_push_page(condition).map(|(r, headers)| (PushPage::from(r), headers))
+    }
+
+    /// Request push history grouped by sender, see [`group_by_sender()`](push/fn.group_by_sender.html).
+    pub fn list_pushes_by_sender(&self, condition: &ListCondition)
+                                 -> ::std::result::Result<HashMap<Option<String>, Vec<Response>>, Box<dyn Error>> {
+        self.list_push(condition).map(|(pushes, _)| group_by_sender(pushes))
+    }
+
+    /// Request one page of push history, calling `f` for each push as it is read from
+    /// the response, and returning the cursor of the next page.
+    ///
+    /// Unlike [`list_push()`](#method.list_push), the page is never held in memory as a
+    /// whole, which keeps memory use low with a large `condition.limit`.
+    pub fn list_push_each<F: FnMut(Response)>(&self, condition: &ListCondition, mut f: F)
+                                              -> ::std::result::Result<(Option<String>, ResponseHeaders), Box<dyn Error>> {
+        debug!("condition: {:?}", condition);
+        let (raw_response, headers) = self.get(&self.pushes_url(condition)?)?;
+        let cursor = for_each_push(BufReader::new(raw_response), &mut f)?;
+        Ok((cursor, headers))
+    }
+
+    /// Get the most recent push, or `None` if there are no pushes.
+    pub fn latest_push(&self) -> ::std::result::Result<Option<Response>, Box<dyn Error>> {
+        let (pushes, _) = self.list_push(&ListCondition::new(1))?;
+        Ok(pushes.into_iter().next())
+    }
+
+    /// Request push history as `(created time, push)` pairs, oldest first.
+    pub fn list_push_timeline(&self, condition: &ListCondition)
+                              -> ::std::result::Result<Vec<TimedPush>, Box<dyn Error>> {
+        let (pushes, _) = self.list_push(condition)?;
+        Ok(timeline(pushes))
+    }
+
+    /// Request pushes modified after `t`.
+    pub fn list_pushes_since(&self, t: DateTime<Utc>, limit: u32)
+                             -> ::std::result::Result<(Vec<Response>, ResponseHeaders), Box<dyn Error>> {
+        let mut condition = ListCondition::new(limit);
+        condition.set_modified_after(t);
+        self.list_push(&condition)
+    }
+
+    /// Request all of the push history, following the cursor across pages.
+    ///
+    /// `condition.limit` is used as the page size.
+    /// Call [`dedup()`](push/struct.AllPushes.html#method.dedup) on the iterator to skip
+    /// pushes that show up on more than one page.
+    pub fn list_all_pushes(&self, condition: &ListCondition) -> AllPushes<'_> {
+        AllPushes {
+            client: self,
+            condition: condition.clone(),
+            pushes: vec![].into_iter(),
+            done: false,
+            seen: None,
+            pause_threshold: DEFAULT_PAUSE_THRESHOLD,
+            max_pause: DEFAULT_MAX_PAUSE,
+            headers: None,
+        }
+    }
+
+    /// Get up to `limit` pushes last modified after `start` and no later than `end`, newest first.
+    ///
+    /// `start` is sent to the API as `modified_after`, while pushes modified after `end`
+    /// are skipped client-side. Paging stops once pushes are older than `start`.
+    pub fn list_pushes_between(&self, start: DateTime<Utc>, end: DateTime<Utc>, limit: u32)
+                               -> ::std::result::Result<Vec<Response>, Box<dyn Error>> {
+        let mut condition = ListCondition::new(limit.min(MAX_PAGE_SIZE));
+        condition.set_modified_after(start);
+        self.list_all_pushes(&condition)
+            .take_while(|push| push.as_ref().map_or(true, |p| p.modified_time() > start))
+            .filter(|push| push.as_ref().map_or(true, |p| p.modified_time() <= end))
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Get up to `limit` pushes with `direction == "outgoing"`.
+    ///
+    /// The API has no direction filter, so pages are fetched until `limit` outgoing
+    /// pushes are found or the history is exhausted, whichever comes first.
+    pub fn list_outgoing(&self, limit: u32) -> ::std::result::Result<Vec<Response>, Box<dyn Error>> {
+        self.list_all_pushes(&ListCondition::new(limit.min(MAX_PAGE_SIZE)))
+            .filter(|push| push.as_ref().map_or(true, |p| p.direction == "outgoing"))
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Request the file pushes that have a `file_url`, following the cursor across pages,
+    /// e.g. for a media viewer.
+    ///
+    /// The API has no type filter, so every page of the history is fetched and uses
+    /// API quota, including pages without any file push.
+    pub fn list_file_pushes(&self, condition: &ListCondition)
+                            -> impl Iterator<Item = ::std::result::Result<Response, Box<dyn Error>>> + '_ {
+        self.list_all_pushes(condition)
+            .filter(|push| push.as_ref().map_or(true, |p| p.push_type == "file" && !p.file_url.is_empty()))
+    }
+
+    /// Request the pushes of one type ("note", "file", "link"), following the cursor
+    /// across pages.
+    ///
+    /// This is a client-side filter: the API has no type filter, so pushes of other
+    /// types are still fetched (and count against `condition.limit`) before being dropped.
+    pub fn list_pushes_of_type<'a>(&'a self, condition: &ListCondition, push_type: &'a str)
+                                   -> impl Iterator<Item = ::std::result::Result<Response, Box<dyn Error>>> + 'a {
+        self.list_all_pushes(condition)
+            .filter(move |push| push.as_ref().map_or(true, |p| p.push_type == push_type))
+    }
+
+    /// Count active pushes, optionally only those modified after `since`.
+    ///
+    /// The API has no count endpoint, so this pages through the whole history
+    /// and uses API quota proportional to the number of pushes.
+    pub fn count_active_pushes(&self, since: Option<DateTime<Utc>>) -> ::std::result::Result<usize, Box<dyn Error>> {
+        let mut condition = ListCondition::new(MAX_PAGE_SIZE);
+        if let Some(t) = since {
+            condition.set_modified_after(t);
+        }
+        let mut count = 0;
+        for push in self.list_all_pushes(&condition) {
+            push?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Request push history as untyped JSON, e.g. to debug a deserialization failure.
+    pub fn list_push_raw(&self, condition: &ListCondition) -> ::std::result::Result<Value, Box<dyn Error>> {
+        debug!("condition: {:?}", condition);
+        let (raw_response, _) = self.get(&self.pushes_url(condition)?)?;
+        Ok(serde_json::from_reader(raw_response)?)
+    }
+
+    /// Request push history from a URL built by the caller, e.g. with query parameters
+    /// not covered by `ListCondition`.
+    ///
+    /// `url` must be a valid URL of the pushes API, such as
+    /// `https://api.pushbullet.com/v2/pushes?active=true&limit=10`: it is sent the
+    /// access token, and the response is read as a list of pushes.
+    pub fn list_push_url(&self, url: Url)
+                         -> ::std::result::Result<(Vec<Response>, ResponseHeaders), Box<dyn Error>> {
+        match self.get(url.as_str()) {
+            Ok((raw_response, headers)) => {
+                let r: ResponseVec = serde_json::from_reader(raw_response)?;
+                Ok((r.pushes, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    fn pushes_url(&self, condition: &ListCondition) -> ::std::result::Result<String, Box<dyn Error>> {
+        Ok(String::from(Url::parse_with_params(&self.api_url(PUSHES_PATH), &condition.query_params())?))
+    }
+
+    fn list_push_page(&self, condition: &ListCondition)
+                      -> ::std::result::Result<(ResponseVec, ResponseHeaders), Box<dyn Error>> {
+        debug!("condition: {:?}", condition);
+        match self.get(&self.pushes_url(condition)?) {
+            Ok((raw_response, headers)) => {
+                let r: ResponseVec = serde_json::from_reader(raw_response)?;
+                Ok((r, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::*;
+
+    #[test]
+    fn deserialize_test() {
+        let deserialized: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
+        assert_eq!(deserialized.pushes.len(), 1);
+        assert_eq!(deserialized.cursor, None);
+        let r = &deserialized.pushes[0];
+        assert_eq!(r.push_type, "note");
+        assert!(r.active);
+        assert_eq!(r.direction, "self");
+
+        assert_eq!(r.modified, 1.412047948579031e+09);
+        //        println!("{}", r.modified_time().to_rfc3339());
+        let diff = r.modified_time().signed_duration_since(Utc.with_ymd_and_hms(2014, 9, 30, 3, 32, 28).unwrap());
+        assert!((diff.num_milliseconds() - 579).abs() < 10);
+
+        assert_eq!(r.created, 1.412047948579029e+09);
+        //        println!("{}", r.created_time().to_rfc3339());
+        let diff = r.created_time().signed_duration_since(Utc.with_ymd_and_hms(2014, 9, 30, 3, 32, 28).unwrap());
+        assert!((diff.num_milliseconds() - 579).abs() < 10);
+    }
+
+    #[test]
+    fn latest_push_test() {
+        let (mut server, client) = mock_client();
+
+        let mock = server.mock("GET", "/pushes?active=true&limit=1")
+            .with_body(r#"{"pushes": []}"#)
+            .create();
+        assert!(client.latest_push().unwrap().is_none());
+        mock.remove();
+
+        server.mock("GET", "/pushes?active=true&limit=1")
+            .with_body(PUSH_RESULT)
+            .create();
+        assert_eq!(client.latest_push().unwrap().unwrap().iden, "ujpah72o0sjAoRtnM0jc");
+    }
+
+    #[test]
+    fn create_push_iden_test() {
+        let (mut server, client) = mock_client();
+        server.mock("POST", "/pushes")
+            .with_body(MINIMAL_PUSH)
+            .create();
+        let iden = client.create_push_iden(&Target::Broadcast, Request::note_body("channel push")).unwrap();
+        assert_eq!(iden, "ujxCHwc6fiSsjAl2PwnrAG");
+    }
+
+    #[test]
+    fn owned_request_test() {
+        let request = OwnedRequest::from(Request::Link { title: "t", body: "b", url: "https://www.pushbullet.com" });
+        assert_eq!(build_push_json(&Target::Broadcast, &request.as_request()), json!({
+            "type": "link",
+            "title": "t",
+            "body": "b",
+            "url": "https://www.pushbullet.com"
+        }));
+    }
+
+    #[test]
+    fn schedule_push_test() {
+        let (mut server, client) = mock_client();
+        let mock = server.mock("POST", "/pushes")
+            .with_body(MINIMAL_PUSH)
+            .expect(1)
+            .create();
+
+        let start = Instant::now();
+        let scheduled = client.schedule_push(Duration::from_millis(100), Target::Broadcast,
+                                             OwnedRequest::from(Request::note_body("later"))).unwrap();
+        let (r, _) = scheduled.join().unwrap().unwrap();
+        assert_eq!(r.iden, "ujxCHwc6fiSsjAl2PwnrAG");
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let scheduled = client.schedule_push(Duration::from_secs(60), Target::Broadcast,
+                                             OwnedRequest::from(Request::note_body("never"))).unwrap();
+        assert!(scheduled.cancel());
+        assert!(!scheduled.cancel());
+        assert!(scheduled.join().is_none());
+
+        let e = client.schedule_push(Duration::from_secs(60), Target::Device(String::new()),
+                                     OwnedRequest::from(Request::note_body("invalid"))).err().unwrap();
+        assert!(matches!(e, PushbulletError::InvalidRequest(_)));
+        mock.assert();
+    }
+
+    #[test]
+    fn get_pushes_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes/ujpah72o0")
+            .with_body(MINIMAL_PUSH)
+            .create();
+        server.mock("GET", "/pushes/gone")
+            .with_status(404)
+            .create();
+
+        let results = client.get_pushes(&["ujpah72o0", "gone"]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap().0.iden, "ujxCHwc6fiSsjAl2PwnrAG");
+        assert!(results[1].is_err());
+    }
+
+    #[test]
+    fn delete_pushes_before_test() {
+        let (mut server, client) = mock_client();
+        let second = IMAGE_FILE_PUSH.replace("ujpah72o0sjAoRtnM0jc", "second");
+        server.mock("GET", "/pushes?active=true&limit=500")
+            .with_body(format!(r#"{{"pushes": [{}, {}, {}]}}"#, IMAGE_FILE_PUSH, MINIMAL_PUSH, second))
+            .expect(2)
+            .create();
+        let deleted = server.mock("DELETE", "/pushes/ujpah72o0sjAoRtnM0jc")
+            .with_body("{}")
+            .expect(2)
+            .create();
+        server.mock("DELETE", "/pushes/second")
+            .with_status(500)
+            .create();
+        let outgoing = server.mock("DELETE", "/pushes/ujxCHwc6fiSsjAl2PwnrAG")
+            .with_body("{}")
+            .create();
+
+        let e = client.delete_pushes_before(Utc::now()).unwrap_err();
+        assert_eq!(e.deleted, 1);
+        assert!(e.error.downcast_ref::<PushbulletError>().is_some());
+
+        let e = client.delete_pushes_before_with_directions(Utc::now(), &["self", "outgoing"]).unwrap_err();
+        assert_eq!(e.deleted, 2);
+        deleted.assert();
+        outgoing.assert();
+    }
+
+    #[test]
+    fn get_pushes_batches_test() {
+        let (mut server, client) = mock_client();
+        let mock = server.mock("GET", "/pushes/ujpah72o0")
+            .with_body(MINIMAL_PUSH)
+            .expect(20)
+            .create();
+        server.mock("GET", "/pushes/gone")
+            .with_status(404)
+            .create();
+
+        let mut idens = vec!["ujpah72o0"; 20];
+        idens.insert(9, "gone");
+        let results = client.get_pushes(&idens);
+        assert_eq!(results.len(), 21);
+        assert!(results.iter().enumerate().all(|(i, r)| r.is_ok() == (i != 9)));
+        mock.assert();
+    }
+
+    #[test]
+    fn idempotency_key_test() {
+        let (mut server, client) = mock_client();
+        let mock = server.mock("POST", "/pushes")
+            .match_header("Idempotency-Key", "notify-42")
+            .with_body(MINIMAL_PUSH)
+            .create();
+        client.create_push_with_idempotency_key(&Target::Broadcast, Request::note_body("hi"), "notify-42").unwrap();
+        mock.assert();
+    }
+
+    #[test]
+    fn get_push_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes/ujxCHwc6fiSsjAl2PwnrAG")
+            .with_body(MINIMAL_PUSH)
+            .create();
+        let (push, _) = client.get_push("ujxCHwc6fiSsjAl2PwnrAG").unwrap();
+        assert_eq!(push.iden, "ujxCHwc6fiSsjAl2PwnrAG");
+        assert_eq!(push.body, "channel push");
+    }
+
+    #[test]
+    fn list_all_pushes_dedup_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes?active=true&limit=10&cursor=next")
+            .with_body(format!(r#"{{"pushes": [{}]}}"#, MINIMAL_PUSH))
+            .create();
+        server.mock("GET", "/pushes?active=true&limit=10")
+            .with_body(format!(r#"{{"pushes": [{}], "cursor": "next"}}"#, MINIMAL_PUSH))
+            .create();
+
+        let condition = ListCondition::new(10);
+        assert_eq!(client.list_all_pushes(&condition).count(), 2);
+        let pushes: Vec<_> = client.list_all_pushes(&condition).dedup().collect();
+        assert_eq!(pushes.len(), 1);
+    }
+
+    #[test]
+    fn list_all_pushes_rate_limit_pause_test() {
+        let (mut server, client) = mock_client();
+        let reset = (Utc::now() + chrono::Duration::seconds(60)).timestamp().to_string();
+        server.mock("GET", "/pushes?active=true&limit=10&cursor=next")
+            .with_body(format!(r#"{{"pushes": [{}]}}"#, MINIMAL_PUSH))
+            .create();
+        server.mock("GET", "/pushes?active=true&limit=10")
+            .with_header("X-Ratelimit-Remaining", "50")
+            .with_header("X-Ratelimit-Reset", &reset)
+            .with_body(format!(r#"{{"pushes": [{}], "cursor": "next"}}"#, MINIMAL_PUSH))
+            .create();
+
+        let condition = ListCondition::new(10);
+        let start = Instant::now();
+        let pushes: Vec<_> = client.list_all_pushes(&condition)
+            .rate_limit_pause(100, Duration::from_millis(200))
+            .collect();
+        assert_eq!(pushes.len(), 2);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let start = Instant::now();
+        let pushes: Vec<_> = client.list_all_pushes(&condition)
+            .rate_limit_pause(0, Duration::from_secs(60))
+            .collect();
+        assert_eq!(pushes.len(), 2);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn check_file_size_test() {
+        assert!(PushbulletClient::check_file_size(100, None).is_ok());
+        assert!(PushbulletClient::check_file_size(26214400, Some(2.62144e+07)).is_ok());
+        match PushbulletClient::check_file_size(26214401, Some(2.62144e+07)) {
+            Err(PushbulletError::FileTooLarge { size, max_upload_size }) => {
+                assert_eq!(size, 26214401);
+                assert_eq!(max_upload_size, 26214400);
+            }
+            r => panic!("unexpected result: {:?}", r)
+        }
+    }
+
+    #[test]
+    fn check_upload_size_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/users/me")
+            .with_body(r#"{"active": true, "iden": "ujpah72o0", "created": 1.4e+09, "modified": 1.4e+09,
+                           "email": "elon@teslamotors.com", "email_normalized": "elon@teslamotors.com",
+                           "max_upload_size": 8}"#)
+            .create();
+        let path = ::std::env::temp_dir().join(format!("pshbullet_client_upload_{}", ::std::process::id()));
+        ::std::fs::write(&path, b"1234").unwrap();
+        assert_eq!(client.check_upload_size(&path).unwrap(), 4);
+        ::std::fs::write(&path, b"123456789").unwrap();
+        let e = client.check_upload_size(&path).unwrap_err();
+        ::std::fs::remove_file(&path).unwrap();
+        assert!(matches!(e.downcast_ref::<PushbulletError>(), Some(PushbulletError::FileTooLarge { size: 9, .. })));
+    }
+
+    #[test]
+    fn list_file_pushes_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes?active=true&limit=10&cursor=next")
+            .with_body(format!(r#"{{"pushes": [{}]}}"#, MINIMAL_PUSH))
+            .create();
+        server.mock("GET", "/pushes?active=true&limit=10")
+            .with_body(format!(r#"{{"pushes": [{}, {}], "cursor": "next"}}"#, IMAGE_FILE_PUSH, MINIMAL_PUSH))
+            .create();
+
+        let pushes = client.list_file_pushes(&ListCondition::new(10))
+            .collect::<::std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(pushes.len(), 1);
+        assert_eq!(pushes[0].push_type, "file");
+        assert!(!pushes[0].file_url.is_empty());
+    }
+
+    #[test]
+    fn create_push_multi_fail_fast_test() {
+        let (mut server, client) = mock_client();
+        let failed = server.mock("POST", "/pushes")
+            .match_body(mockito::Matcher::PartialJson(json!({"device_iden": "bad"})))
+            .with_status(400)
+            .expect(1)
+            .create();
+        let sent = server.mock("POST", "/pushes")
+            .with_body(MINIMAL_PUSH)
+            .expect(1)
+            .create();
+
+        let targets = [Target::Device(String::from("good")),
+                       Target::Device(String::from("bad")),
+                       Target::Device(String::from("never"))];
+        assert!(client.create_push_multi_fail_fast(&targets, &Request::note_body("hi")).is_err());
+        failed.assert();
+        sent.assert();
+    }
+
+    #[test]
+    fn push_to_emails_test() {
+        let (mut server, client) = mock_client();
+        server.mock("POST", "/pushes")
+            .match_body(mockito::Matcher::PartialJson(json!({"email": "nobody"})))
+            .with_status(400)
+            .create();
+        server.mock("POST", "/pushes")
+            .with_body(MINIMAL_PUSH)
+            .create();
+
+        let results = client.push_to_emails(&["nobody", "elon@teslamotors.com"], &Request::note_body("hi"));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "nobody");
+        assert!(results[0].1.is_err());
+        assert_eq!(results[1].0, "elon@teslamotors.com");
+        assert!(results[1].1.is_ok());
+    }
+
+    #[test]
+    fn list_pushes_between_test() {
+        let (mut server, client) = mock_client();
+        let pushes: Vec<String> = ["1.4125e+09", "1.4121e+09", "1.412047948579031e+09", "1.41e+09"].iter()
+            .map(|modified| MINIMAL_PUSH.replace("1.412047948579031e+09", modified))
+            .collect();
+        server.mock("GET", "/pushes?active=true&limit=10&modified_after=1411948800")
+            .with_body(format!(r#"{{"pushes": [{}], "cursor": "next"}}"#, pushes.join(",")))
+            .create();
+
+        let start = Utc.with_ymd_and_hms(2014, 9, 29, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2014, 10, 1, 0, 0, 0).unwrap();
+        let between = client.list_pushes_between(start, end, 10).unwrap();
+        assert_eq!(between.len(), 2);
+        assert_eq!(between[0].modified, 1.4121e+09);
+        assert_eq!(between[1].modified, 1.412047948579031e+09);
+    }
+
+    #[test]
+    fn group_by_sender_test() {
+        let mut pushes = serde_json::from_str::<ResponseVec>(PUSH_RESULT).unwrap().pushes;
+        pushes.push(serde_json::from_str(MINIMAL_PUSH).unwrap());
+        pushes.push(serde_json::from_str(MINIMAL_PUSH).unwrap());
+        let groups = group_by_sender(pushes);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&Some(String::from("ujpah72o0"))].len(), 1);
+        assert_eq!(groups[&None].len(), 2);
+    }
+
+    #[test]
+    fn invalid_base_url_test() {
+        let client = PushbulletClient::new(String::from("token")).with_base_url("not a url");
+        assert!(client.list_push(&ListCondition::new(10)).is_err());
+        assert!(client.list_all_pushes(&ListCondition::new(10)).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn list_push_url_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes?active=true&undocumented=1")
+            .with_body(PUSH_RESULT)
+            .create();
+        let url = Url::parse(&format!("{}/pushes?active=true&undocumented=1", server.url())).unwrap();
+        let (pushes, _) = client.list_push_url(url).unwrap();
+        assert_eq!(pushes.len(), 1);
+    }
+
+    #[test]
+    fn for_each_push_test() {
+        let paged = PUSH_RESULT.replacen("{", r#"{ "cursor": "ujpah72o0sjAoRtnM0jc", "unknown": [{}],"#, 1);
+        let mut idens = vec![];
+        let cursor = for_each_push(paged.as_bytes(), &mut |p: Response| idens.push(p.iden)).unwrap();
+        assert_eq!(cursor, Some(String::from("ujpah72o0sjAoRtnM0jc")));
+        assert_eq!(idens, vec!["ujpah72o0sjAoRtnM0jc"]);
+
+        let mut count = 0;
+        let cursor = for_each_push(PUSH_RESULT.as_bytes(), &mut |_| count += 1).unwrap();
+        assert_eq!(cursor, None);
+        assert_eq!(count, 1);
+
+        assert!(for_each_push(r#"{"pushes": [{}]}"#.as_bytes(), &mut |_| ()).is_err());
+    }
+
+    #[test]
+    fn list_push_each_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes?active=true&limit=10")
+            .with_body(PUSH_RESULT)
+            .create();
+        let mut pushes = vec![];
+        let (cursor, _) = client.list_push_each(&ListCondition::new(10), |p| pushes.push(p)).unwrap();
+        assert_eq!(cursor, None);
+        assert_eq!(pushes.len(), 1);
+    }
+
+    #[test]
+    fn timeline_test() {
+        let newer: Response = serde_json::from_str(MINIMAL_PUSH).unwrap();
+        let older: Response = serde_json::from_str(
+            &MINIMAL_PUSH.replace("1.412047948579029e+09", "1.3e+09")).unwrap();
+        let timeline = timeline(vec![newer, older]);
+        assert_eq!(timeline[0].1.created, 1.3e+09);
+        assert_eq!(timeline[1].1.created, 1.412047948579029e+09);
+        assert!(timeline[0].0 < timeline[1].0);
+    }
+
+    #[test]
+    fn only_incoming_test() {
+        let deserialized: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
+        assert!(only_incoming(deserialized.pushes).is_empty());
+
+        let incoming = PUSH_RESULT.replace(r#""direction": "self""#, r#""direction": "incoming""#);
+        let deserialized: ResponseVec = serde_json::from_str(&incoming).unwrap();
+        assert_eq!(only_incoming(deserialized.pushes).len(), 1);
+    }
+
+    #[test]
+    fn direction_test() {
+        let deserialized: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
+        assert_eq!(deserialized.pushes[0].direction_kind(), Direction::ToSelf);
+        assert_eq!(Direction::from("outgoing"), Direction::Outgoing);
+        assert_eq!(Direction::from("incoming"), Direction::Incoming);
+        assert_eq!(Direction::from("sideways"), Direction::Other(String::from("sideways")));
+    }
+
+    #[test]
+    fn person_test() {
+        let deserialized: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
+        let r = &deserialized.pushes[0];
+        let sender = r.sender();
+        assert_eq!(sender.name, Some("Elon Musk"));
+        assert_eq!(sender.email, "elon@teslamotors.com");
+        assert_eq!(sender.iden, "ujpah72o0");
+        assert_eq!(r.receiver().name, None);
+        assert_eq!(r.receiver().iden, "ujpah72o0");
+    }
+
+    #[test]
+    fn deserialize_minimal_test() {
+        let r: Response = serde_json::from_str(MINIMAL_PUSH).unwrap();
+        assert_eq!(r.iden, "ujxCHwc6fiSsjAl2PwnrAG");
+        assert_eq!(r.receiver_email, "");
+        assert_eq!(r.sender_name, "");
+        assert_eq!(r.title, "");
+        assert_eq!(r.channel_iden, None);
+    }
+
+    #[test]
+    fn deserialize_channel_push_test() {
+        let channel_push = MINIMAL_PUSH.replacen("{", r#"{ "channel_iden": "ujxCHwc6fiSsjAl2PwnrAG", "client_iden": "ubdcjAfszs0Smi","#, 1);
+        let r: Response = serde_json::from_str(&channel_push).unwrap();
+        assert_eq!(r.channel_iden, Some(String::from("ujxCHwc6fiSsjAl2PwnrAG")));
+        assert_eq!(r.client_iden, Some(String::from("ubdcjAfszs0Smi")));
+    }
+
+    #[test]
+    fn deserialize_image_file_push_test() {
+        let r: Response = serde_json::from_str(IMAGE_FILE_PUSH).unwrap();
+        assert_eq!(r.push_type, "file");
+        assert_eq!(r.image_width, Some(1280));
+        assert_eq!(r.image_height, Some(960));
+
+        let r: Response = serde_json::from_str(MINIMAL_PUSH).unwrap();
+        assert_eq!(r.image_width, None);
+    }
+
+    #[test]
+    fn summary_test() {
+        let r: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
+        assert_eq!(r.pushes[0].summary(), r#"[note] "Space Travel Ideas" from Elon Musk"#);
+
+        let r: Response = serde_json::from_str(MINIMAL_PUSH).unwrap();
+        assert_eq!(r.summary(), r#"[note] "channel push""#);
+
+        let r: Response = serde_json::from_str(IMAGE_FILE_PUSH).unwrap();
+        assert_eq!(r.summary(), "[file]");
+    }
+
+    #[test]
+    fn to_request_test() {
+        let r: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
+        let request = r.pushes[0].to_request().unwrap();
+        assert_eq!(build_push_json(&Target::Device(String::from("ujpah72o0sjAoRtnM0jc")), &request), json!({
+            "type": "note",
+            "title": "Space Travel Ideas",
+            "body": "Space Elevator, Mars Hyperloop, Space Model S (Model Space?)",
+            "device_iden": "ujpah72o0sjAoRtnM0jc"
+        }));
+
+        let r: Response = serde_json::from_str(IMAGE_FILE_PUSH).unwrap();
+        let json = build_push_json(&Target::Broadcast, &r.to_request().unwrap());
+        assert_eq!(json["type"], "file");
+        assert_eq!(json["file_name"], "cat.jpg");
+        assert_eq!(json["file_url"], "https://dl.pushbulletusercontent.com/foGfub1jtC6yYcOMACk1AbHwTrTKvrDc/cat.jpg");
+
+        let r: Response = serde_json::from_str(&MINIMAL_PUSH.replace(r#""note""#, r#""mirror""#)).unwrap();
+        assert!(r.to_request().is_err());
+    }
+
+    #[test]
+    fn deserialize_guid_test() {
+        let r: Response = serde_json::from_str(MINIMAL_PUSH).unwrap();
+        assert_eq!(r.guid, None);
+        assert_eq!(r.awake_app_guids, None);
+
+        let with_guid = MINIMAL_PUSH.replacen("{", r#"{ "guid": "993aaa48567d91068e96c75a74644159", "awake_app_guids": ["web-2d8cdf2a2b9b", "web-7a9d22a2c6ef"],"#, 1);
+        let r: Response = serde_json::from_str(&with_guid).unwrap();
+        assert_eq!(r.guid, Some(String::from("993aaa48567d91068e96c75a74644159")));
+        assert_eq!(r.awake_app_guids, Some(vec![String::from("web-2d8cdf2a2b9b"), String::from("web-7a9d22a2c6ef")]));
+    }
+
+    #[test]
+    fn list_pushes_of_type_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes?active=true&limit=10")
+            .with_body(format!(r#"{{"pushes": [{}, {}]}}"#, MINIMAL_PUSH, IMAGE_FILE_PUSH))
+            .create();
+
+        let notes: Vec<_> = client.list_pushes_of_type(&ListCondition::new(10), "note")
+            .collect::<::std::result::Result<_, _>>().unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].iden, "ujxCHwc6fiSsjAl2PwnrAG");
+        assert_eq!(client.list_pushes_of_type(&ListCondition::new(10), "link").count(), 0);
+    }
+
+    #[test]
+    fn note_body_test() {
+        let json = build_push_json(&Target::Broadcast, &Request::note_body("just a body"));
+        assert_eq!(json, json!({"type": "note", "body": "just a body"}));
+
+        let json = build_push_json(&Target::Broadcast, &Request::Note { title: "t", body: "b" });
+        assert_eq!(json["title"], "t");
+    }
+
+    #[test]
+    fn smart_request_test() {
+        let request = Request::smart("t", "b", Some("https://www.pushbullet.com/"));
+        assert!(matches!(request, Request::Link { url: "https://www.pushbullet.com/", .. }));
+        let request = Request::smart("t", "b", None);
+        assert!(matches!(request, Request::Note { title: "t", body: "b" }));
+    }
+
+    #[test]
+    fn deserialize_cursor_test() {
+        let paged = PUSH_RESULT.replacen("{", r#"{ "cursor": "ujpah72o0sjAoRtnM0jc","#, 1);
+        let deserialized: ResponseVec = serde_json::from_str(&paged).unwrap();
+        assert_eq!(deserialized.cursor, Some(String::from("ujpah72o0sjAoRtnM0jc")));
+        let page = PushPage::from(deserialized);
+        assert!(page.has_more);
+        assert_eq!(page.pushes.len(), 1);
+
+        let last: ResponseVec = serde_json::from_str(PUSH_RESULT).unwrap();
+        assert!(!PushPage::from(last).has_more);
+    }
+
+    #[test]
+    fn modified_after_raw_test() {
+        let modified = 1.412047948579031e+09;
+        let mut condition = ListCondition::new(10);
+        condition.set_modified_after_raw(modified);
+        assert_eq!(condition.modified_after, Some(modified));
+
+        condition.set_modified_after(float_unix_time2date_time(modified));
+        let round_tripped = condition.modified_after.unwrap();
+        assert!((round_tripped - modified).abs() < 1e-6);
+    }
+
+    #[test]
+    fn format_modified_param_test() {
+        let t = Utc.with_ymd_and_hms(2014, 9, 30, 3, 32, 28).unwrap() + chrono::Duration::milliseconds(579);
+        let mut condition = ListCondition::new(10);
+        condition.set_modified_after(t);
+        assert!(condition.query_params().contains(&("modified_after", format_modified_param(t))));
+    }
+
+    #[test]
+    fn zero_limit_test() {
+        let params = ListCondition::new(10).query_params();
+        assert_eq!(params, vec![("active", String::from("true")), ("limit", String::from("10"))]);
+
+        let params = ListCondition::new(0).query_params();
+        assert_eq!(params, vec![("active", String::from("true"))]);
+    }
+
+    #[test]
+    fn age_test() {
+        let mut r: Response = serde_json::from_str(MINIMAL_PUSH).unwrap();
+        assert!(r.age() > chrono::Duration::days(365));
+        r.created = date_time2float_unix_time(Utc::now());
+        let age = r.age();
+        assert!(age >= chrono::Duration::zero() && age < chrono::Duration::seconds(1), "{}", age);
+    }
+
+    #[test]
+    fn including_deleted_test() {
+        let params = ListCondition::including_deleted(10).query_params();
+        assert_eq!(params, vec![("active", String::from("false")), ("limit", String::from("10"))]);
+
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/pushes?active=false&limit=10")
+            .with_body(format!(r#"{{"pushes": [{}, {}]}}"#, DELETED_PUSH, MINIMAL_PUSH))
+            .create();
+        let (pushes, _) = client.list_push(&ListCondition::including_deleted(10)).unwrap();
+        assert_eq!(pushes.len(), 2);
+        assert!(!pushes[0].active);
+        assert_eq!(pushes[0].iden, "ujpah72o0sjAoRtnM0jc");
+        assert_eq!(pushes[0].push_type, "");
+        assert!(pushes[1].active);
+    }
+
+    #[test]
+    fn default_list_condition_test() {
+        let condition = ListCondition::default();
+        assert!(condition.active);
+        assert_eq!(condition.limit, 20);
+        assert_eq!(condition.modified_after, None);
+        assert_eq!(condition.cursor, None);
+
+        let condition = ListCondition { limit: 50, ..Default::default() };
+        assert_eq!(condition.query_params(), vec![("active", String::from("true")), ("limit", String::from("50"))]);
+    }
+
+    #[test]
+    fn target_json_key_test() {
+        let request = Request::note_body("b");
+        let json = build_push_json(&Target::Broadcast, &request);
+        assert_eq!(json.as_object().unwrap().len(), 2);
+        let json = build_push_json(&Target::Device(String::from("d")), &request);
+        assert_eq!(json["device_iden"], "d");
+        let json = build_push_json(&Target::Email(String::from("e@example.com")), &request);
+        assert_eq!(json["email"], "e@example.com");
+        let json = build_push_json(&Target::channel("elonmusk"), &request);
+        assert_eq!(json["channel_tag"], "elonmusk");
+        let json = build_push_json(&Target::Client(String::from("c")), &request);
+        assert_eq!(json["client_iden"], "c");
+    }
+
+    #[test]
+    fn apply_target_test() {
+        let mut json = json!({"type": "note", "body": "b", "email": "e@example.com"});
+        apply_target(&mut json, &Target::Device(String::from("d")));
+        assert_eq!(json, json!({"type": "note", "body": "b", "device_iden": "d"}));
+
+        apply_target(&mut json, &Target::Broadcast);
+        assert_eq!(json, json!({"type": "note", "body": "b"}));
+    }
+
+    #[test]
+    fn create_push_with_extra_target_test() {
+        let (mut server, client) = mock_client();
+        let mock = server.mock("POST", "/pushes")
+            .match_body(mockito::Matcher::Json(json!({"type": "note", "body": "b", "device_iden": "d"})))
+            .with_body(MINIMAL_PUSH)
+            .create();
+        let mut extra = serde_json::Map::new();
+        extra.insert(String::from("email"), json!("e@example.com"));
+        client.create_push_with_extra(&Target::Device(String::from("d")), Request::note_body("b"), extra).unwrap();
+        mock.assert();
+    }
+
+    #[test]
+    fn validate_channel_tag_test() {
+        assert!(Target::channel("elonmusk").validate().is_ok());
+        match Target::channel("").validate() {
+            Err(PushbulletError::InvalidRequest(_)) => (),
+            r => panic!("unexpected result: {:?}", r)
+        }
+    }
+
+    #[test]
+    fn merge_extra_test() {
+        let json = build_push_json(&Target::Broadcast, &Request::Note { title: "t", body: "b" });
+        let mut extra = serde_json::Map::new();
+        extra.insert(String::from("source_device_iden"), json!("ujpah72o0sjAoRtnM0jc"));
+        extra.insert(String::from("title"), json!("overridden"));
+        assert_eq!(merge_extra(json, extra), json!({
+            "type": "note",
+            "title": "overridden",
+            "body": "b",
+            "source_device_iden": "ujpah72o0sjAoRtnM0jc"
+        }));
+    }
+
+    #[test]
+    fn validate_target_test() {
+        assert!(Target::Broadcast.validate().is_ok());
+        assert!(Target::Device(String::from("ujpah72o0sjAoRtnM0jc")).validate().is_ok());
+        assert!(Target::Email(String::from("elon@teslamotors.com")).validate().is_ok());
+        for target in &[Target::Device(String::new()),
+                        Target::Email(String::from(" ")),
+                        Target::Client(String::from("ujpah72o0 sjAoRtnM0jc")),
+                        Target::channel("elonmusk\n")] {
+            match target.validate() {
+                Err(PushbulletError::InvalidRequest(_)) => (),
+                r => panic!("unexpected result for {:?}: {:?}", target, r)
+            }
+        }
+    }
+
+    #[test]
+    fn target_from_str_test() {
+        assert!(matches!("broadcast".parse(), Ok(Target::Broadcast)));
+        assert!(matches!("device:ujpah72o0sjAoRtnM0jc".parse(), Ok(Target::Device(ref iden)) if iden == "ujpah72o0sjAoRtnM0jc"));
+        assert!(matches!("email:elon@teslamotors.com".parse(), Ok(Target::Email(ref email)) if email == "elon@teslamotors.com"));
+        assert!(matches!("channel:elonmusk".parse(), Ok(Target::Channel(ref tag)) if tag == "elonmusk"));
+        assert!(matches!("client:ubdcjAfszs0Smi".parse(), Ok(Target::Client(ref iden)) if iden == "ubdcjAfszs0Smi"));
+        for s in &["phone:123", "device", "device:", "broadcast:x", ""] {
+            match s.parse::<Target>() {
+                Err(PushbulletError::InvalidRequest(_)) => (),
+                r => panic!("unexpected result for {:?}: {:?}", s, r)
+            }
+        }
+    }
+
+    #[test]
+    fn resume_token_test() {
+        let mut condition = ListCondition::new(100);
+        condition.set_modified_after_raw(1.412047948579031e+09);
+        condition.cursor = Some(String::from("ujpah72o0sjAoRtnM0jc"));
+        let token = condition.to_resume_token();
+
+        let resumed = ListCondition::from_resume_token(&token).unwrap();
+        assert!(resumed.active);
+        assert_eq!(resumed.limit, 100);
+        assert_eq!(resumed.modified_after, Some(1.412047948579031e+09));
+        assert_eq!(resumed.cursor, Some(String::from("ujpah72o0sjAoRtnM0jc")));
+        assert_eq!(resumed.to_resume_token(), token);
+
+        assert!(ListCondition::from_resume_token("not a token").is_err());
+    }
+
+    #[test]
+    fn validate_link_url_test() {
+        let link = Request::Link { title: "t", body: "b", url: "https://www.rust-lang.org/" };
+        assert!(link.validate().is_ok());
+
+        let link = Request::Link { title: "t", body: "b", url: "www.rust-lang.org" };
+        match link.validate() {
+            Err(PushbulletError::InvalidRequest(reason)) => assert!(reason.contains("www.rust-lang.org")),
+            r => panic!("unexpected result: {:?}", r)
+        }
+    }
+
+    #[test]
+    fn download_file_test() {
+        let (mut server, client) = mock_client();
+        let redirect = server.mock("GET", "/redirect")
+            .with_status(302)
+            .with_header("Location", "/file.txt")
+            .create();
+        let file = server.mock("GET", "/file.txt")
+            .match_header("Access-Token", mockito::Matcher::Missing)
+            .with_body("file content")
+            .create();
+        let dest = std::env::temp_dir().join(format!("download_file_test_{}", std::process::id()));
+
+        let written = client.download_file(&format!("{}/redirect", server.url()), &dest).unwrap();
+        assert_eq!(written, 12);
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "file content");
+        redirect.assert();
+        file.assert();
+        fs::remove_file(&dest).unwrap();
+    }
+
+    #[test]
+    fn download_file_error_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/missing.txt")
+            .with_status(404)
+            .create();
+        let dest = std::env::temp_dir().join(format!("download_file_error_test_{}", std::process::id()));
+
+        assert!(client.download_file(&format!("{}/missing.txt", server.url()), &dest).is_err());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn may_send_token_test() {
+        let host = |url: &str| may_send_token(&Url::parse(url).unwrap());
+        assert!(host("https://dl.pushbulletusercontent.com/foGfub1jtC/image.jpg"));
+        assert!(host("https://api.pushbullet.com/v2/pushes"));
+        assert!(!host("https://example.com/image.jpg"));
+        assert!(!host("https://pushbullet.com.example.com/image.jpg"));
+        assert!(!host("https://notpushbullet.com/image.jpg"));
+        assert!(!host("http://dl.pushbulletusercontent.com/foGfub1jtC/image.jpg"));
+        assert!(!host("http://api.pushbullet.com/v2/pushes"));
+    }
+
+    #[test]
+    fn http_redirect_token_test() {
+        // a redirect downgrading to http is resolved like in download_file() and loses the token
+        let url = Url::parse("https://dl.pushbulletusercontent.com/foGfub1jtC/image.jpg").unwrap();
+        assert!(may_send_token(&url));
+        let redirected = url.join("http://dl.pushbulletusercontent.com/foGfub1jtC/image.jpg").unwrap();
+        assert!(!may_send_token(&redirected));
+        let redirected = url.join("//dl.pushbulletusercontent.com/other.jpg").unwrap();
+        assert!(may_send_token(&redirected));
+    }
+
+    #[test]
+    fn modified_after_param_test() {
+        let t = Utc.with_ymd_and_hms(2014, 9, 30, 3, 32, 28).unwrap() + chrono::Duration::microseconds(579_031);
+        let mut condition = ListCondition::new(10);
+        condition.set_modified_after(t);
+        let modified_after = condition.modified_after.unwrap();
+
+        let param = format_timestamp_param(modified_after);
+        assert_eq!(param, "1412047948.579031");
+        assert_eq!(param.parse::<f64>().unwrap(), modified_after);
+        let diff = float_unix_time2date_time(param.parse().unwrap()).signed_duration_since(t);
+        assert!(diff.num_microseconds().unwrap().abs() < 1);
+    }
+
+    #[cfg(feature = "idempotent")]
+    #[test]
+    fn is_retryable_test() {
+        let headers = || ResponseHeaders { ratelimit_limit: None, ratelimit_remaining: None, ratelimit_reset: None };
+        let rate_limited = PushbulletError::Http { status: reqwest::StatusCode::TOO_MANY_REQUESTS, headers: headers(), body: String::new() };
+        assert!(is_retryable(&rate_limited));
+        let bad_request = PushbulletError::Http { status: reqwest::StatusCode::BAD_REQUEST, headers: headers(), body: String::new() };
+        assert!(!is_retryable(&bad_request));
+        let parse_error = serde_json::from_str::<Response>("{}").unwrap_err();
+        assert!(!is_retryable(&parse_error));
+        let connect_error = reqwest::blocking::get("http://127.0.0.1:1/").unwrap_err();
+        assert!(is_retryable(&connect_error));
+        let builder_error = reqwest::blocking::get("not a url").unwrap_err();
+        assert!(!is_retryable(&builder_error));
+    }
+
+    #[cfg(feature = "idempotent")]
+    #[test]
+    fn idempotent_retry_delay_test() {
+        let now = Utc.with_ymd_and_hms(2017, 6, 7, 17, 30, 0).unwrap();
+        let rate_limited = |reset: i64| PushbulletError::Http {
+            status: reqwest::StatusCode::TOO_MANY_REQUESTS,
+            headers: ResponseHeaders { ratelimit_limit: None, ratelimit_remaining: Some(0), ratelimit_reset: Some(reset) },
+            body: String::new()
+        };
+        let reset = now.timestamp();
+        assert_eq!(idempotent_retry_delay(&rate_limited(reset + 10), 1, now), Duration::from_secs(10));
+        assert_eq!(idempotent_retry_delay(&rate_limited(reset + 3600), 1, now), MAX_IDEMPOTENT_WAIT);
+        // a reset time in the past falls back to the backoff
+        assert_eq!(idempotent_retry_delay(&rate_limited(reset - 10), 2, now), Duration::from_secs(4));
+        let timeout = PushbulletError::InvalidRequest(String::new());
+        assert_eq!(idempotent_retry_delay(&timeout, 3, now), Duration::from_secs(8));
+    }
+
+    const DELETED_PUSH: &str = r#"
+{
+  "active": false,
+  "created": 1.412047948579029e+09,
+  "iden": "ujpah72o0sjAoRtnM0jc",
+  "modified": 1.412047948579031e+09
+}
+    "#;
+
+    const MINIMAL_PUSH: &str = r#"
+{
+  "active": true,
+  "created": 1.412047948579029e+09,
+  "modified": 1.412047948579031e+09,
+  "direction": "outgoing",
+  "dismissed": false,
+  "iden": "ujxCHwc6fiSsjAl2PwnrAG",
+  "type": "note",
+  "body": "channel push"
+}
+    "#;
+
+    const IMAGE_FILE_PUSH: &str = r#"
+{
+  "active": true,
+  "created": 1.412047948579029e+09,
+  "modified": 1.412047948579031e+09,
+  "direction": "self",
+  "dismissed": false,
+  "iden": "ujpah72o0sjAoRtnM0jc",
+  "type": "file",
+  "file_name": "cat.jpg",
+  "file_type": "image/jpeg",
+  "file_url": "https://dl.pushbulletusercontent.com/foGfub1jtC6yYcOMACk1AbHwTrTKvrDc/cat.jpg",
+  "image_url": "https://lh3.googleusercontent.com/mrrz2Gh-6F1u1ABCDEFGhijkLMN",
+  "image_width": 1280,
+  "image_height": 960
+}
+    "#;
 
     const PUSH_RESULT: &str = r#"
 {
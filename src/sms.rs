@@ -0,0 +1,185 @@
+//! Reading SMS threads of a phone
+//!
+//! The threads are stored as permanents of the phone's device.
+//! See [`PushbulletClient::list_sms_threads()`](../struct.PushbulletClient.html#method.list_sms_threads)
+//! or [`PushbulletClient::list_sms_messages()`](../struct.PushbulletClient.html#method.list_sms_messages)
+
+use super::*;
+
+
+/// Other party of an SMS thread
+#[derive(Deserialize, Debug)]
+pub struct SmsRecipient {
+    /// Name of the contact
+    #[serde(default)]
+    pub name: String,
+    /// Address of the contact, usually the phone number
+    #[serde(default)]
+    pub address: String,
+    /// Phone number of the contact
+    #[serde(default)]
+    pub number: String,
+}
+
+/// Message in an SMS thread
+#[derive(Deserialize, Debug)]
+pub struct SmsMessage {
+    /// Identifier of the message on the phone
+    pub id: String,
+    /// Type of the message, e.g. "sms" or "mms"
+    #[serde(rename = "type")]
+    pub message_type: String,
+    /// Time of the message in seconds (unix timestamp)
+    pub timestamp: i64,
+    /// "incoming" or "outgoing"
+    pub direction: String,
+    /// Text of the message
+    #[serde(default)]
+    pub body: String,
+    /// Delivery status of an outgoing message
+    pub status: Option<String>,
+}
+
+impl SmsMessage {
+    /// Get `timestamp` field as DateTime.
+    pub fn time(&self) -> DateTime<Utc> {
+        float_unix_time2date_time(self.timestamp as f64)
+    }
+}
+
+/// SMS conversation on a phone
+#[derive(Deserialize, Debug)]
+pub struct SmsThread {
+    /// Identifier of the thread on the phone
+    pub id: String,
+    /// People taking part in the thread
+    #[serde(default)]
+    pub recipients: Vec<SmsRecipient>,
+    /// Most recent message of the thread
+    pub latest: Option<SmsMessage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ThreadsPermanent {
+    pub threads: Vec<SmsThread>
+}
+
+#[derive(Deserialize, Debug)]
+struct ThreadPermanent {
+    pub thread: Vec<SmsMessage>
+}
+
+impl PushbulletClient {
+    /// Get the SMS threads of the phone `device_iden`.
+    pub fn list_sms_threads(&self, device_iden: &str) -> Result<Vec<SmsThread>, Box<dyn Error>> {
+        let value = self.get_permanent(&format!("{}_threads", device_iden))?;
+        let r: ThreadsPermanent = serde_json::from_value(value)?;
+        Ok(r.threads)
+    }
+
+    /// Check whether the device `device_iden` can send SMS messages, i.e. it is an active
+    /// device reporting `has_sms`.
+    ///
+    /// Returns `Ok(false)` if the user has no such device.
+    pub fn can_send_sms(&self, device_iden: &str) -> Result<bool, Box<dyn Error>> {
+        let (devices, _) = self.list_devices()?;
+        Ok(devices.iter()
+            .any(|d| d.iden == device_iden && d.active && d.has_sms == Some(true)))
+    }
+
+    /// Get the messages of the SMS thread `thread_id` on the phone `device_iden`.
+    pub fn list_sms_messages(&self, device_iden: &str, thread_id: &str) -> Result<Vec<SmsMessage>, Box<dyn Error>> {
+        let value = self.get_permanent(&format!("{}_thread_{}", device_iden, thread_id))?;
+        let r: ThreadPermanent = serde_json::from_value(value)?;
+        Ok(r.thread)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::*;
+
+    #[test]
+    fn deserialize_threads_test() {
+        let deserialized: ThreadsPermanent = serde_json::from_str(THREADS_RESULT).unwrap();
+        assert_eq!(deserialized.threads.len(), 1);
+        let thread = &deserialized.threads[0];
+        assert_eq!(thread.id, "3");
+        assert_eq!(thread.recipients[0].name, "Elon Musk");
+        let latest = thread.latest.as_ref().unwrap();
+        assert_eq!(latest.body, "Sounds good.");
+        assert_eq!(latest.time(), Utc.with_ymd_and_hms(2015, 10, 3, 2, 50, 58).unwrap());
+    }
+
+    #[test]
+    fn deserialize_messages_test() {
+        let deserialized: ThreadPermanent = serde_json::from_str(THREAD_RESULT).unwrap();
+        assert_eq!(deserialized.thread.len(), 2);
+        assert_eq!(deserialized.thread[0].direction, "incoming");
+        assert_eq!(deserialized.thread[0].status, None);
+        assert_eq!(deserialized.thread[1].message_type, "sms");
+        assert_eq!(deserialized.thread[1].status, Some(String::from("sent")));
+    }
+
+    #[test]
+    fn can_send_sms_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/devices")
+            .with_body(r#"{"devices": [
+                {"active": true, "iden": "phone", "created": 1.4e+09, "modified": 1.4e+09, "icon": "phone", "has_sms": true},
+                {"active": true, "iden": "laptop", "created": 1.4e+09, "modified": 1.4e+09, "icon": "laptop"}
+            ]}"#)
+            .create();
+        assert!(client.can_send_sms("phone").unwrap());
+        assert!(!client.can_send_sms("laptop").unwrap());
+        assert!(!client.can_send_sms("unknown").unwrap());
+    }
+
+    const THREADS_RESULT: &str = r#"
+{
+  "threads": [
+    {
+      "id": "3",
+      "latest": {
+        "body": "Sounds good.",
+        "direction": "incoming",
+        "id": "17",
+        "timestamp": 1443840658,
+        "type": "sms"
+      },
+      "recipients": [
+        {
+          "address": "+13035551212",
+          "name": "Elon Musk",
+          "number": "+13035551212"
+        }
+      ]
+    }
+  ]
+}
+    "#;
+
+    const THREAD_RESULT: &str = r#"
+{
+  "thread": [
+    {
+      "body": "Sounds good.",
+      "direction": "incoming",
+      "id": "17",
+      "timestamp": 1443840658,
+      "type": "sms"
+    },
+    {
+      "body": "Meet at the launch pad?",
+      "direction": "outgoing",
+      "id": "16",
+      "status": "sent",
+      "timestamp": 1443840601,
+      "type": "sms"
+    }
+  ]
+}
+    "#;
+}
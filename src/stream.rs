@@ -0,0 +1,534 @@
+//! Pushbullet realtime event stream
+//!
+//! A websocket that notifies about changes as they happen. Requires the `stream` feature.
+//! See [`PushbulletClient::connect_stream()`](../struct.PushbulletClient.html#method.connect_stream)
+//! or [`PushbulletClient::connect_stream_with_heartbeat_timeout()`](../struct.PushbulletClient.html#method.connect_stream_with_heartbeat_timeout)
+//! or [`PushbulletClient::connect_stream_resilient()`](../struct.PushbulletClient.html#method.connect_stream_resilient)
+
+use super::*;
+use std::io;
+use std::net::TcpStream;
+use std::thread;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+
+const STREAM_URL: &str = "wss://stream.pushbullet.com/websocket/";
+
+/// Heartbeat timeout of `connect_stream_resilient()`, three missed `Nop`s
+const RESILIENT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Longest wait between reconnection attempts of `connect_stream_resilient()`
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Message received from the stream
+#[derive(Debug)]
+pub enum StreamEvent {
+    /// Heartbeat, sent by the server about every 30 seconds
+    Nop,
+    /// Something changed on the server, e.g. subtype "push" or "device".
+    /// The change itself has to be fetched, e.g. with `list_push()`.
+    Tickle(String),
+    /// Ephemeral sent to the user, the inner `push` object of the message
+    Push(PushEphemeral),
+    /// Message of a type not known to this crate
+    Unknown(Value),
+    /// The connection was lost and has been re-established. Changes in between were
+    /// missed and should be fetched, e.g. with `list_push()`.
+    ///
+    /// Only emitted by [`ResilientEventStream`](struct.ResilientEventStream.html).
+    Reconnected,
+}
+
+/// Notification of a new SMS on a phone
+#[derive(Deserialize, Debug)]
+pub struct SmsNotification {
+    /// Identifier of the SMS thread, see [`PushbulletClient::list_sms_messages()`](../struct.PushbulletClient.html#method.list_sms_messages)
+    pub thread_id: String,
+    /// Name or number of the sender
+    #[serde(default)]
+    pub title: String,
+    /// Text of the message
+    #[serde(default)]
+    pub body: String,
+    /// Time of the message in seconds (unix timestamp)
+    pub timestamp: i64,
+}
+
+/// The SMS notifications of a phone changed
+#[derive(Deserialize, Debug)]
+pub struct SmsChanged {
+    /// Iden of the phone
+    pub source_device_iden: String,
+    /// Current SMS notifications of the phone
+    #[serde(default)]
+    pub notifications: Vec<SmsNotification>,
+}
+
+/// A mirrored notification was dismissed on one of the user's devices
+#[derive(Deserialize, Debug)]
+pub struct DismissedNotification {
+    /// Package name of the Android app that posted the notification
+    pub package_name: String,
+    /// Android notification id of the notification
+    pub notification_id: String,
+    /// Android notification tag of the notification, if it has one
+    pub notification_tag: Option<String>,
+    /// User iden of the account the notification was mirrored from
+    pub source_user_iden: String,
+}
+
+/// Clipboard contents copied on one of the user's devices (universal copy & paste)
+#[derive(Deserialize, Debug)]
+pub struct Clip {
+    /// Copied text
+    pub body: String,
+    /// User iden of the user who copied it
+    pub source_user_iden: String,
+    /// Iden of the device it was copied on
+    pub source_device_iden: String,
+}
+
+/// Inner push of a [`StreamEvent::Push`](enum.StreamEvent.html#variant.Push), by its `type`
+#[derive(Debug)]
+pub enum PushEphemeral {
+    /// "sms_changed"
+    Sms(SmsChanged),
+    /// "dismissal"
+    Dismissal(DismissedNotification),
+    /// "clip"
+    Clip(Clip),
+    /// Any other type, or a known type with unexpected fields
+    Unknown(Value),
+}
+
+impl From<Value> for PushEphemeral {
+    fn from(push: Value) -> PushEphemeral {
+        let parsed = match push["type"].as_str() {
+            Some("sms_changed") => serde_json::from_value(push.clone()).map(PushEphemeral::Sms),
+            Some("dismissal") => serde_json::from_value(push.clone()).map(PushEphemeral::Dismissal),
+            Some("clip") => serde_json::from_value(push.clone()).map(PushEphemeral::Clip),
+            _ => return PushEphemeral::Unknown(push)
+        };
+        parsed.unwrap_or_else(|e| {
+            warn!("failed to parse ephemeral: {}", e);
+            PushEphemeral::Unknown(push)
+        })
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl PushEphemeral {
+    /// Decrypt a push that was encrypted end-to-end, see [`encryption`](../encryption/index.html).
+    ///
+    /// Any other push, or one that fails to decrypt with `key`, is returned as is.
+    pub fn decrypt(self, key: &encryption::Key) -> PushEphemeral {
+        let ciphertext = match self {
+            PushEphemeral::Unknown(ref push) if push["encrypted"] == true =>
+                push["ciphertext"].as_str().map(str::to_owned),
+            _ => None
+        };
+        let ciphertext = match ciphertext {
+            Some(ciphertext) => ciphertext,
+            None => return self
+        };
+        let decrypted = key.decrypt(&ciphertext)
+            .and_then(|plaintext| Ok(serde_json::from_str::<Value>(&plaintext)?));
+        match decrypted {
+            Ok(push) => PushEphemeral::from(push),
+            Err(e) => {
+                warn!("failed to decrypt ephemeral: {}", e);
+                self
+            }
+        }
+    }
+}
+
+/// Encryption key of the client that a stream was connected with, if any
+#[derive(Clone, Default)]
+struct Decryptor {
+    #[cfg(feature = "encryption")]
+    key: Option<encryption::Key>,
+}
+
+impl Decryptor {
+    #[cfg_attr(not(feature = "encryption"), allow(unused_variables))]
+    fn of(client: &PushbulletClient) -> Decryptor {
+        Decryptor {
+            #[cfg(feature = "encryption")]
+            key: client.encryption_key.clone(),
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    fn decrypt(&self, event: StreamEvent) -> StreamEvent {
+        match (event, &self.key) {
+            (StreamEvent::Push(push), Some(key)) => StreamEvent::Push(push.decrypt(key)),
+            (event, _) => event
+        }
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn decrypt(&self, event: StreamEvent) -> StreamEvent {
+        event
+    }
+}
+
+impl StreamEvent {
+    fn parse(text: &str) -> ::std::result::Result<StreamEvent, serde_json::Error> {
+        let mut message: Value = serde_json::from_str(text)?;
+        let event = match message["type"].as_str() {
+            Some("nop") => StreamEvent::Nop,
+            Some("tickle") => StreamEvent::Tickle(message["subtype"].as_str().unwrap_or_default().to_owned()),
+            Some("push") => StreamEvent::Push(PushEphemeral::from(message["push"].take())),
+            _ => StreamEvent::Unknown(message)
+        };
+        Ok(event)
+    }
+}
+
+/// Iterator over the events of the stream. It ends when the server closes the connection.
+///
+/// With an encryption password set on the client, encrypted pushes are decrypted.
+/// See [`PushbulletClient::connect_stream()`](../struct.PushbulletClient.html#method.connect_stream)
+pub struct EventStream {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    heartbeat_timeout: Option<Duration>,
+    decryptor: Decryptor,
+    done: bool,
+}
+
+impl EventStream {
+    fn connect(url: &str, heartbeat_timeout: Option<Duration>, decryptor: Decryptor)
+               -> ::std::result::Result<EventStream, Box<dyn Error>> {
+        let (socket, _) = tungstenite::connect(url)?;
+        match *socket.get_ref() {
+            MaybeTlsStream::Plain(ref s) => s.set_read_timeout(heartbeat_timeout)?,
+            MaybeTlsStream::NativeTls(ref s) => s.get_ref().set_read_timeout(heartbeat_timeout)?,
+            #[allow(unreachable_patterns)]
+            _ => ()
+        }
+        Ok(EventStream { socket, heartbeat_timeout, decryptor, done: false })
+    }
+
+    fn read_event(&mut self) -> Option<::std::result::Result<StreamEvent, Box<dyn Error>>> {
+        loop {
+            match self.socket.read() {
+                Ok(Message::Text(text)) => {
+                    trace!("stream message: {}", text);
+                    return Some(StreamEvent::parse(&text)
+                        .map(|event| self.decryptor.decrypt(event))
+                        .map_err(From::from));
+                }
+                Ok(_) => (),
+                Err(tungstenite::Error::ConnectionClosed) | Err(tungstenite::Error::AlreadyClosed) => return None,
+                Err(tungstenite::Error::Io(ref e))
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+                    let message = format!("no message from the stream in {:?}", self.heartbeat_timeout.unwrap_or_default());
+                    return Some(Err(From::from(io::Error::new(io::ErrorKind::TimedOut, message))));
+                }
+                Err(e) => return Some(Err(From::from(e)))
+            }
+        }
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = ::std::result::Result<StreamEvent, Box<dyn Error>>;
+
+    /// After an error, the iterator ends.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.read_event();
+        self.done = !matches!(event, Some(Ok(_)));
+        event
+    }
+}
+
+/// Iterator over the events of the stream that reconnects when the connection is lost.
+///
+/// See [`PushbulletClient::connect_stream_resilient()`](../struct.PushbulletClient.html#method.connect_stream_resilient)
+pub struct ResilientEventStream {
+    url: String,
+    decryptor: Decryptor,
+    stream: Option<EventStream>,
+    failures: u32,
+    done: bool,
+}
+
+impl ResilientEventStream {
+    fn reconnect_delay(&self) -> Duration {
+        Duration::from_secs(1)
+            .checked_mul(1 << (self.failures - 1).min(16))
+            .map_or(RECONNECT_MAX_DELAY, |delay| delay.min(RECONNECT_MAX_DELAY))
+    }
+}
+
+/// Whether the server refused the websocket handshake, e.g. because of an invalid token
+fn is_rejected(e: &(dyn Error + 'static)) -> bool {
+    match e.downcast_ref::<tungstenite::Error>() {
+        Some(tungstenite::Error::Http(response)) => response.status().is_client_error(),
+        _ => false
+    }
+}
+
+impl Iterator for ResilientEventStream {
+    type Item = ::std::result::Result<StreamEvent, Box<dyn Error>>;
+
+    /// Connection errors are retried, so the only error is a refused handshake, after
+    /// which the iterator ends.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let stream = match self.stream {
+                Some(ref mut stream) => stream,
+                None => {
+                    if self.failures > 0 {
+                        let delay = self.reconnect_delay();
+                        info!("reconnecting to the stream in {:?}", delay);
+                        thread::sleep(delay);
+                    }
+                    match EventStream::connect(&self.url, Some(RESILIENT_HEARTBEAT_TIMEOUT), self.decryptor.clone()) {
+                        Ok(stream) => {
+                            self.stream = Some(stream);
+                            return Some(Ok(StreamEvent::Reconnected));
+                        }
+                        Err(e) => {
+                            if is_rejected(e.as_ref()) {
+                                self.done = true;
+                                return Some(Err(e));
+                            }
+                            warn!("failed to reconnect to the stream: {}", e);
+                            self.failures += 1;
+                            continue;
+                        }
+                    }
+                }
+            };
+            match stream.next() {
+                Some(Ok(event)) => {
+                    self.failures = 0;
+                    return Some(Ok(event));
+                }
+                Some(Err(e)) => warn!("stream error: {}", e),
+                None => info!("stream closed by the server")
+            }
+            self.stream = None;
+            self.failures += 1;
+        }
+    }
+}
+
+impl PushbulletClient {
+    /// Connect to the realtime event stream.
+    pub fn connect_stream(&self) -> ::std::result::Result<EventStream, Box<dyn Error>> {
+        EventStream::connect(&self.stream_url(), None, Decryptor::of(self))
+    }
+
+    /// Connect to the realtime event stream, failing with a `TimedOut` I/O error if no
+    /// message arrives within `timeout`.
+    ///
+    /// The server sends a [`Nop`](stream/enum.StreamEvent.html#variant.Nop) about every
+    /// 30 seconds, so a timeout of a minute or so detects a dead connection.
+    pub fn connect_stream_with_heartbeat_timeout(&self, timeout: Duration)
+                                                 -> ::std::result::Result<EventStream, Box<dyn Error>> {
+        EventStream::connect(&self.stream_url(), Some(timeout), Decryptor::of(self))
+    }
+
+    /// Connect to the realtime event stream, reconnecting whenever the connection is lost,
+    /// e.g. for a long-running daemon.
+    ///
+    /// After each reconnection a [`Reconnected`](stream/enum.StreamEvent.html#variant.Reconnected)
+    /// event is emitted. Reconnection attempts back off exponentially up to a minute apart,
+    /// and a connection without any message for 90 seconds is considered dead.
+    /// Only the first connection attempt and a handshake refused by the server fail.
+    pub fn connect_stream_resilient(&self) -> ::std::result::Result<ResilientEventStream, Box<dyn Error>> {
+        let url = self.stream_url();
+        let decryptor = Decryptor::of(self);
+        let stream = EventStream::connect(&url, Some(RESILIENT_HEARTBEAT_TIMEOUT), decryptor.clone())?;
+        Ok(ResilientEventStream { url, decryptor, stream: Some(stream), failures: 0, done: false })
+    }
+
+    fn stream_url(&self) -> String {
+        format!("{}{}", STREAM_URL, self.access_token)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Serve a single websocket connection on localhost, sending `messages` then waiting `linger`.
+    fn serve(messages: Vec<&'static str>, linger: Duration) -> String {
+        serve_each(vec![messages], linger)
+    }
+
+    /// Serve one websocket connection on localhost per entry of `connections`, one after another.
+    fn serve_each(connections: Vec<Vec<&'static str>>, linger: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/websocket/token", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for messages in connections {
+                let (tcp, _) = listener.accept().unwrap();
+                let mut socket = tungstenite::accept(tcp).unwrap();
+                for message in messages {
+                    socket.send(Message::Text(message.to_owned())).unwrap();
+                }
+                thread::sleep(linger);
+                let _ = socket.close(None);
+                let _ = socket.flush();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn parse_event_test() {
+        assert!(matches!(StreamEvent::parse(r#"{"type": "nop"}"#).unwrap(), StreamEvent::Nop));
+        match StreamEvent::parse(r#"{"type": "tickle", "subtype": "push"}"#).unwrap() {
+            StreamEvent::Tickle(subtype) => assert_eq!(subtype, "push"),
+            e => panic!("unexpected event: {:?}", e)
+        }
+        match StreamEvent::parse(r#"{"type": "push", "push": {"type": "mirror", "body": "copied"}}"#).unwrap() {
+            StreamEvent::Push(PushEphemeral::Unknown(push)) => assert_eq!(push["body"], "copied"),
+            e => panic!("unexpected event: {:?}", e)
+        }
+        assert!(matches!(StreamEvent::parse(r#"{"type": "new"}"#).unwrap(), StreamEvent::Unknown(_)));
+        assert!(StreamEvent::parse("not json").is_err());
+    }
+
+    #[test]
+    fn sms_ephemeral_test() {
+        match PushEphemeral::from(json!({
+            "type": "sms_changed",
+            "source_device_iden": "ujpah72o0sjAoRtnM0jc",
+            "notifications": [{
+                "thread_id": "3",
+                "title": "Elon Musk",
+                "body": "Sounds good.",
+                "timestamp": 1443840658,
+                "image_url": "https://dl.pushbulletusercontent.com/musk.jpg"
+            }]
+        })) {
+            PushEphemeral::Sms(sms) => {
+                assert_eq!(sms.source_device_iden, "ujpah72o0sjAoRtnM0jc");
+                assert_eq!(sms.notifications[0].thread_id, "3");
+                assert_eq!(sms.notifications[0].body, "Sounds good.");
+            }
+            e => panic!("unexpected ephemeral: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn dismissal_ephemeral_test() {
+        match PushEphemeral::from(json!({
+            "type": "dismissal",
+            "package_name": "com.pushbullet.android",
+            "notification_id": "-8",
+            "notification_tag": null,
+            "source_user_iden": "ujpah72o0"
+        })) {
+            PushEphemeral::Dismissal(dismissal) => {
+                assert_eq!(dismissal.package_name, "com.pushbullet.android");
+                assert_eq!(dismissal.notification_tag, None);
+            }
+            e => panic!("unexpected ephemeral: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn clip_ephemeral_test() {
+        match PushEphemeral::from(json!({
+            "type": "clip",
+            "body": "http://www.google.com",
+            "source_user_iden": "ujpah72o0",
+            "source_device_iden": "ujpah72o0sjAoRtnM0jc"
+        })) {
+            PushEphemeral::Clip(clip) => assert_eq!(clip.body, "http://www.google.com"),
+            e => panic!("unexpected ephemeral: {:?}", e)
+        }
+        // a known type with missing fields is kept as is
+        assert!(matches!(PushEphemeral::from(json!({"type": "clip"})), PushEphemeral::Unknown(_)));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn decrypt_ephemeral_test() {
+        let key = encryption::Key::derive("hunter2", "ujpah72o0");
+        let clip = r#"{"type": "clip", "body": "copied", "source_user_iden": "ujpah72o0", "source_device_iden": "ujpah72o0sjAoRtnM0jc"}"#;
+        let encrypted = json!({"encrypted": true, "ciphertext": key.encrypt(clip)});
+        match PushEphemeral::from(encrypted.clone()).decrypt(&key) {
+            PushEphemeral::Clip(clip) => assert_eq!(clip.body, "copied"),
+            e => panic!("unexpected ephemeral: {:?}", e)
+        }
+        // a wrong key leaves the push as is
+        let other = encryption::Key::derive("hunter3", "ujpah72o0");
+        assert!(matches!(PushEphemeral::from(encrypted).decrypt(&other),
+                         PushEphemeral::Unknown(ref push) if push["encrypted"] == true));
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn encrypted_event_stream_test() {
+        let mut client = PushbulletClient::new(String::from("token"));
+        client.set_encryption_password("hunter2", "ujpah72o0");
+        // {"type":"clip","body":"hi"} encrypted with this password
+        let url = serve(vec![r#"{"type": "push", "push": {"encrypted": true, "ciphertext": "MQiUyNBv+t/xPvUGVeBwqoYAAQIDBAUGBwgJCgvA9DBz+sjOhGzOV9SJ9RZvohUmLiFKId4h/CI="}}"#],
+                        Duration::from_millis(0));
+        let events: Vec<_> = EventStream::connect(&url, None, Decryptor::of(&client)).unwrap().collect();
+        match events[0] {
+            Ok(StreamEvent::Push(PushEphemeral::Unknown(ref push))) => {
+                assert_eq!(push["type"], "clip");
+                assert_eq!(push["body"], "hi");
+            }
+            ref e => panic!("unexpected event: {:?}", e)
+        }
+    }
+
+    #[test]
+    fn event_stream_test() {
+        let url = serve(vec![r#"{"type": "nop"}"#, r#"{"type": "tickle", "subtype": "device"}"#],
+                        Duration::from_millis(0));
+        let events: Vec<_> = EventStream::connect(&url, None, Decryptor::default()).unwrap().collect();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], Ok(StreamEvent::Nop)));
+        assert!(matches!(events[1], Ok(StreamEvent::Tickle(ref subtype)) if subtype == "device"));
+    }
+
+    #[test]
+    fn resilient_stream_test() {
+        let url = serve_each(vec![vec![r#"{"type": "nop"}"#], vec![r#"{"type": "tickle", "subtype": "push"}"#]],
+                             Duration::from_millis(0));
+        let stream = EventStream::connect(&url, None, Decryptor::default()).unwrap();
+        let mut resilient = ResilientEventStream { url, decryptor: Decryptor::default(), stream: Some(stream), failures: 0, done: false };
+        assert!(matches!(resilient.next(), Some(Ok(StreamEvent::Nop))));
+        assert!(matches!(resilient.next(), Some(Ok(StreamEvent::Reconnected))));
+        assert!(matches!(resilient.next(), Some(Ok(StreamEvent::Tickle(ref subtype))) if subtype == "push"));
+    }
+
+    #[test]
+    fn reconnect_delay_test() {
+        let mut resilient = ResilientEventStream { url: String::new(), decryptor: Decryptor::default(), stream: None, failures: 1, done: false };
+        assert_eq!(resilient.reconnect_delay(), Duration::from_secs(1));
+        resilient.failures = 3;
+        assert_eq!(resilient.reconnect_delay(), Duration::from_secs(4));
+        resilient.failures = 40;
+        assert_eq!(resilient.reconnect_delay(), RECONNECT_MAX_DELAY);
+    }
+
+    #[test]
+    fn heartbeat_timeout_test() {
+        let url = serve(vec![r#"{"type": "nop"}"#], Duration::from_secs(2));
+        let mut stream = EventStream::connect(&url, Some(Duration::from_millis(200)), Decryptor::default()).unwrap();
+        assert!(matches!(stream.next(), Some(Ok(StreamEvent::Nop))));
+        let e = stream.next().unwrap().unwrap_err();
+        assert_eq!(e.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::TimedOut);
+        assert!(stream.next().is_none());
+    }
+}
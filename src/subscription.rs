@@ -0,0 +1,243 @@
+//! Pushbullet Subscription API
+//!
+//! See [`PushbulletClient::list_subscriptions()`](../struct.PushbulletClient.html#method.list_subscriptions)
+//! or [`PushbulletClient::mute_subscription()`](../struct.PushbulletClient.html#method.mute_subscription)
+//! or [`PushbulletClient::get_channel_info()`](../struct.PushbulletClient.html#method.get_channel_info)
+//! or [`PushbulletClient::list_owned_channels()`](../struct.PushbulletClient.html#method.list_owned_channels)
+
+use super::*;
+use reqwest::Url;
+
+
+const SUBSCRIPTIONS_PATH: &str = "subscriptions";
+const CHANNEL_INFO_PATH: &str = "channel-info";
+const CHANNELS_PATH: &str = "channels";
+
+/// Channel that a subscription belongs to
+#[derive(Deserialize, Debug)]
+pub struct Channel {
+    /// Unique identifier for the channel
+    pub iden: String,
+    /// Unique tag for this channel
+    pub tag: String,
+    /// Name of the channel
+    pub name: String,
+    /// Description of the channel
+    pub description: Option<String>,
+    /// Image for the channel
+    pub image_url: Option<String>,
+    /// Link to a website for the channel
+    pub website_url: Option<String>,
+    /// Number of subscribers, only returned by the channel-info API
+    pub subscriber_count: Option<u64>,
+}
+
+/// Response type of the subscription API
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    /// `false` if the item has been deleted
+    pub active: bool,
+    /// Unique identifier for this object
+    pub iden: String,
+    /// Creation time in floating point seconds (unix timestamp)
+    pub created: f64,
+    /// Last modified time in floating point seconds (unix timestamp)
+    pub modified: f64,
+    /// If `true`, receive no notifications for pushes to this channel
+    #[serde(default)]
+    pub muted: bool,
+    /// Information about the channel that is being subscribed to
+    pub channel: Option<Channel>,
+}
+
+impl Response {
+    /// Get `created` field as DateTime.
+    pub fn created_time(&self) -> DateTime<Utc> {
+        float_unix_time2date_time(self.created)
+    }
+
+    /// Get `modified` field as DateTime.
+    pub fn modified_time(&self) -> DateTime<Utc> {
+        float_unix_time2date_time(self.modified)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ResponseVec {
+    pub subscriptions: Vec<Response>
+}
+
+#[derive(Deserialize, Debug)]
+struct ChannelVec {
+    pub channels: Vec<Channel>
+}
+
+pub type Result = ::std::result::Result<(Response, ResponseHeaders), Box<dyn Error>>;
+
+fn mute_json(muted: bool) -> Value {
+    json!({ "muted": muted })
+}
+
+impl PushbulletClient {
+    /// Get a list of channel subscriptions belonging to the current user.
+    pub fn list_subscriptions(&self)
+                              -> ::std::result::Result<(Vec<Response>, ResponseHeaders), Box<dyn Error>> {
+        match self.get(&self.api_url(SUBSCRIPTIONS_PATH)) {
+            Ok((raw_response, headers)) => {
+                let r: ResponseVec = serde_json::from_reader(raw_response)?;
+                Ok((r.subscriptions, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Get information about the channel with the tag `channel_tag`.
+    ///
+    /// Fails with a 400 error status if there is no such channel.
+    pub fn get_channel_info(&self, channel_tag: &str)
+                            -> ::std::result::Result<(Channel, ResponseHeaders), Box<dyn Error>> {
+        let url = String::from(Url::parse_with_params(&self.api_url(CHANNEL_INFO_PATH), &[("tag", channel_tag)])?);
+        match self.get(&url) {
+            Ok((raw_response, headers)) => {
+                let r: Channel = serde_json::from_reader(raw_response)?;
+                Ok((r, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Get the channels created by the current user.
+    pub fn list_owned_channels(&self) -> ::std::result::Result<Vec<Channel>, Box<dyn Error>> {
+        match self.get(&self.api_url(CHANNELS_PATH)) {
+            Ok((raw_response, _)) => {
+                let r: ChannelVec = serde_json::from_reader(raw_response)?;
+                Ok(r.channels)
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Send a push to all subscribers of the channel `channel_tag`, which the user must own.
+    ///
+    /// Same as `create_push(&Target::Channel(..), ..)`. To check that the channel
+    /// exists beforehand, use [`get_channel_info()`](#method.get_channel_info).
+    pub fn push_to_channel(&self, channel_tag: &str, request: &push::Request) -> push::Result {
+        self.create_push(&push::Target::channel(channel_tag), request.clone())
+    }
+
+    /// Mute or unmute a subscription, returning the updated subscription.
+    pub fn mute_subscription(&self, iden: &str, muted: bool) -> Result {
+        let url = format!("{}/{}", self.api_url(SUBSCRIPTIONS_PATH), iden);
+        match self.post(&url, mute_json(muted)) {
+            Ok((raw_response, headers)) => {
+                let r: Response = serde_json::from_reader(raw_response)?;
+                Ok((r, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::*;
+
+    #[test]
+    fn deserialize_subscriptions_test() {
+        let deserialized: ResponseVec = serde_json::from_str(SUBSCRIPTIONS_RESULT).unwrap();
+        assert_eq!(deserialized.subscriptions.len(), 1);
+        let r = &deserialized.subscriptions[0];
+        assert!(!r.muted);
+        let channel = r.channel.as_ref().unwrap();
+        assert_eq!(channel.tag, "elonmusk");
+        assert_eq!(channel.website_url, None);
+    }
+
+    #[test]
+    fn get_channel_info_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/channel-info?tag=elonmusk")
+            .with_body(CHANNEL_INFO_RESULT)
+            .create();
+        let (channel, _) = client.get_channel_info("elonmusk").unwrap();
+        assert_eq!(channel.iden, "ujxCHwc6fiSsjAl2PwnrAG");
+        assert_eq!(channel.subscriber_count, Some(9382));
+    }
+
+    #[test]
+    fn deserialize_channels_test() {
+        let deserialized: ChannelVec = serde_json::from_str(CHANNELS_RESULT).unwrap();
+        assert_eq!(deserialized.channels.len(), 1);
+        let channel = &deserialized.channels[0];
+        assert_eq!(channel.iden, "ujxCHwc6fiSsjAl2PwnrAG");
+        assert_eq!(channel.tag, "elonmusk");
+        assert_eq!(channel.name, "Elon Musk Status");
+        assert_eq!(channel.description.as_deref(), Some("Sends out status updates for Elon Musk's ideas."));
+        assert!(channel.image_url.is_some());
+    }
+
+    #[test]
+    fn list_owned_channels_test() {
+        let (mut server, client) = mock_client();
+        server.mock("GET", "/channels")
+            .with_body(CHANNELS_RESULT)
+            .create();
+        let channels = client.list_owned_channels().unwrap();
+        assert_eq!(channels[0].tag, "elonmusk");
+    }
+
+    #[test]
+    fn mute_json_test() {
+        assert_eq!(mute_json(true).to_string(), r#"{"muted":true}"#);
+        assert_eq!(mute_json(false).to_string(), r#"{"muted":false}"#);
+    }
+
+    const CHANNEL_INFO_RESULT: &str = r#"
+{
+  "iden": "ujxCHwc6fiSsjAl2PwnrAG",
+  "name": "Elon Musk Status",
+  "tag": "elonmusk",
+  "description": "Sends out status updates for Elon Musk's ideas.",
+  "image_url": "https://dl.pushbulletusercontent.com/StzRmwdkIe8gluBH3XoJ9HjRqjlUYSf4/musk.jpg",
+  "subscriber_count": 9382,
+  "recent_pushes": []
+}
+    "#;
+
+    const CHANNELS_RESULT: &str = r#"
+{
+  "channels": [
+    {
+      "iden": "ujxCHwc6fiSsjAl2PwnrAG",
+      "tag": "elonmusk",
+      "name": "Elon Musk Status",
+      "description": "Sends out status updates for Elon Musk's ideas.",
+      "image_url": "https://dl.pushbulletusercontent.com/StzRmwdkIe8gluBH3XoJ9HjRqjlUYSf4/musk.jpg"
+    }
+  ]
+}
+    "#;
+
+    const SUBSCRIPTIONS_RESULT: &str = r#"
+{
+  "subscriptions": [
+    {
+      "active": true,
+      "channel": {
+        "description": "Sends out status updates for Elon Musk's ideas.",
+        "iden": "ujxCHwc6fiSsjAl2PwnrAG",
+        "image_url": "https://dl.pushbulletusercontent.com/StzRmwdkIe8gluBH3XoJ9HjRqjlUYSf4/musk.jpg",
+        "name": "Elon Musk Status",
+        "tag": "elonmusk"
+      },
+      "created": 1.4116133109694e+09,
+      "iden": "ujpah72o0sjAoRtnM0jc",
+      "modified": 1.4116133109694e+09,
+      "muted": false
+    }
+  ]
+}
+    "#;
+}
@@ -0,0 +1,130 @@
+//! Pushbullet User API
+//!
+//! See [`PushbulletClient::get_user_info()`](../struct.PushbulletClient.html#method.get_user_info)
+
+use super::*;
+use std::time::Instant;
+
+
+const USERS_ME_PATH: &str = "users/me";
+
+/// Response type of the user API
+#[derive(Deserialize, Debug)]
+pub struct Response {
+    /// Unique identifier for the current user
+    pub iden: String,
+    /// Creation time in floating point seconds (unix timestamp)
+    pub created: f64,
+    /// Last modified time in floating point seconds (unix timestamp)
+    pub modified: f64,
+    /// Email address
+    pub email: String,
+    /// Canonical email address
+    pub email_normalized: String,
+    /// Full name if available
+    #[serde(default)]
+    pub name: String,
+    /// URL for image of user or placeholder image
+    #[serde(default)]
+    pub image_url: String,
+    /// Maximum upload size in bytes
+    pub max_upload_size: Option<f64>,
+}
+
+impl Response {
+    /// Get `created` field as DateTime.
+    pub fn created_time(&self) -> DateTime<Utc> {
+        float_unix_time2date_time(self.created)
+    }
+
+    /// Get `modified` field as DateTime.
+    pub fn modified_time(&self) -> DateTime<Utc> {
+        float_unix_time2date_time(self.modified)
+    }
+}
+
+pub type Result = ::std::result::Result<(Response, ResponseHeaders), Box<dyn Error>>;
+
+impl PushbulletClient {
+    /// Get the currently logged in user.
+    pub fn get_user_info(&self) -> Result {
+        match self.get(&self.api_url(USERS_ME_PATH)) {
+            Ok((raw_response, headers)) => {
+                let r: Response = serde_json::from_reader(raw_response)?;
+                Ok((r, headers))
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Time a request of the current user, e.g. for a readiness probe.
+    ///
+    /// Returns the round-trip time until the response headers are received, or `Err`
+    /// on an error status or network problem. With [`with_retry()`](#method.with_retry),
+    /// the time includes retries.
+    pub fn ping(&self) -> ::std::result::Result<Duration, Box<dyn Error>> {
+        let start = Instant::now();
+        self.get(&self.api_url(USERS_ME_PATH))?;
+        Ok(start.elapsed())
+    }
+
+    /// Check whether the access token is accepted by the API.
+    ///
+    /// Returns `Ok(false)` if the API rejects the token (401 or 403), and `Err` for
+    /// any other failure, such as a network problem.
+    pub fn verify_token(&self) -> ::std::result::Result<bool, Box<dyn Error>> {
+        match self.get_user_info() {
+            Ok(_) => Ok(true),
+            Err(e) => match e.downcast_ref::<PushbulletError>() {
+                Some(&PushbulletError::InvalidToken) => Ok(false),
+                Some(&PushbulletError::Http { status, .. })
+                if status == reqwest::StatusCode::FORBIDDEN => Ok(false),
+                _ => Err(e)
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use super::*;
+
+    #[test]
+    fn deserialize_user_test() {
+        let r: Response = serde_json::from_str(USER_RESULT).unwrap();
+        assert_eq!(r.iden, "ujpah72o0");
+        assert_eq!(r.name, "Elon Musk");
+        assert_eq!(r.max_upload_size, Some(26214400.0));
+        assert_eq!(r.created_time().year(), 2013);
+    }
+
+    #[test]
+    fn ping_test() {
+        let (mut server, client) = mock_client();
+        let mock = server.mock("GET", "/users/me")
+            .with_body(USER_RESULT)
+            .create();
+        assert!(client.ping().is_ok());
+        mock.remove();
+
+        server.mock("GET", "/users/me")
+            .with_status(503)
+            .create();
+        assert!(client.ping().is_err());
+    }
+
+    const USER_RESULT: &str = r#"
+{
+  "created": 1.381092887398433e+09,
+  "email": "elon@teslamotors.com",
+  "email_normalized": "elon@teslamotors.com",
+  "iden": "ujpah72o0",
+  "image_url": "https://static.pushbullet.com/missing-image/55a7dc-45",
+  "max_upload_size": 2.62144e+07,
+  "modified": 1.441054560741007e+09,
+  "name": "Elon Musk"
+}
+    "#;
+}